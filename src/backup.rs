@@ -0,0 +1,31 @@
+use crate::context::Context;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Copy the world directory into backups/<level name>-<timestamp>
+pub fn backup_world(context: &Context, level_name: &str) -> io::Result<PathBuf> {
+    let destination = context.path("backups").join(format!(
+        "{}-{}",
+        level_name,
+        context.clock.now().format("%Y-%m-%d_%H-%M-%S")
+    ));
+    copy_directory(&context.path(level_name), &destination)?;
+    Ok(destination)
+}
+
+// Recursively copy a directory
+fn copy_directory(source: &Path, destination: &Path) -> io::Result<()> {
+    fs::create_dir_all(destination)?;
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let target = destination.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_directory(&entry.path(), &target)?;
+        } else if entry.file_name() != "session.lock" {
+            // session.lock is held open by the server
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
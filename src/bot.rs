@@ -0,0 +1,422 @@
+use crate::clock::Clock;
+use crate::context::Context;
+use crate::control::{self, Control};
+use crate::properties;
+use crate::secrets::{self, Secret};
+use crate::status::{self, SharedStatus};
+use serde_json::{json, Value};
+use std::fs;
+use std::io::ErrorKind;
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::{Duration, Instant};
+use tungstenite::client::AutoStream;
+use tungstenite::stream::Stream;
+use tungstenite::{Message, WebSocket};
+
+const DEFAULT_API_URL: &str = "https://discord.com/api/v10";
+
+// Discord bot settings, from the Discord bot token and server.properties
+pub struct BotConfig {
+    token: String,
+    application_id: String,
+    guild_id: Option<String>,
+    api_url: String,
+    // Roles allowed to use /extend, /stop and /start
+    control_roles: Vec<String>,
+    // Roles allowed to use /status and /players, everyone if empty
+    view_roles: Vec<String>,
+    clock: Clock,
+}
+
+impl BotConfig {
+    // Bot mode is enabled by setting the bot token, usually in discord.token
+    pub fn load(context: &Context) -> Option<BotConfig> {
+        let token = secrets::get(context, Secret::DiscordToken)?;
+        let contents = fs::read_to_string(context.path("server.properties")).unwrap_or_default();
+        let application_id = match properties::get(&contents, "bot-application-id") {
+            Some(application_id) => application_id.to_owned(),
+            None => {
+                println!("[WARN] bot-application-id missing from server.properties, Discord bot disabled");
+                return None;
+            }
+        };
+        let roles = |key| -> Vec<String> {
+            properties::get(&contents, key)
+                .unwrap_or_default()
+                .split(',')
+                .map(|role| role.trim().to_owned())
+                .filter(|role| !role.is_empty())
+                .collect()
+        };
+        Some(BotConfig {
+            token,
+            application_id,
+            guild_id: properties::get(&contents, "bot-guild-id").map(str::to_owned),
+            api_url: properties::get(&contents, "bot-api-url")
+                .unwrap_or(DEFAULT_API_URL)
+                .trim_end_matches('/')
+                .to_owned(),
+            control_roles: roles("bot-control-roles"),
+            view_roles: roles("bot-view-roles"),
+            clock: context.clock,
+        })
+    }
+}
+
+// Run the Discord bot on a background thread
+pub fn spawn(config: BotConfig, controls: Sender<Control>, status: SharedStatus) {
+    thread::spawn(move || run(config, controls, status));
+}
+
+// Register slash commands, then stay connected to the gateway
+fn run(config: BotConfig, controls: Sender<Control>, status: SharedStatus) {
+    let client = reqwest::blocking::Client::new();
+    match register_commands(&client, &config) {
+        Ok(()) => println!("[INFO] Discord bot commands registered"),
+        Err(error) => println!("[WARN] Failed to register Discord bot commands: {}", error),
+    }
+    loop {
+        if let Err(error) = connect_gateway(&client, &config, &controls, &status) {
+            println!("[WARN] Discord gateway connection lost: {}", error);
+        }
+        // Wait a bit before reconnecting
+        thread::sleep(Duration::from_secs(5));
+    }
+}
+
+// Create or update the slash commands, scoped to the guild when one is set
+fn register_commands(client: &reqwest::blocking::Client, config: &BotConfig) -> Result<(), String> {
+    let path = match &config.guild_id {
+        Some(guild_id) => format!(
+            "/applications/{}/guilds/{}/commands",
+            config.application_id, guild_id
+        ),
+        None => format!("/applications/{}/commands", config.application_id),
+    };
+    let commands = json!([
+        {"name": "status", "description": "Show the server status", "type": 1},
+        {"name": "players", "description": "List online players", "type": 1},
+        {"name": "extend", "description": "Push the scheduled shutdown back", "type": 1, "options": [
+            {"type": 3, "name": "duration", "description": "Such as 30m or 1h", "required": true}
+        ]},
+        {"name": "stop", "description": "Stop the server", "type": 1},
+        {"name": "start", "description": "Start the server", "type": 1}
+    ]);
+    let response = client
+        .put(format!("{}{}", config.api_url, path))
+        .header("Authorization", format!("Bot {}", config.token))
+        .json(&commands)
+        .send()
+        .map_err(|error| error.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+    Ok(())
+}
+
+// Stay connected to the gateway, handling interactions until the connection drops
+fn connect_gateway(
+    client: &reqwest::blocking::Client,
+    config: &BotConfig,
+    controls: &Sender<Control>,
+    status: &SharedStatus,
+) -> Result<(), String> {
+    // Ask for the gateway URL
+    let gateway = client
+        .get(format!("{}/gateway", config.api_url))
+        .send()
+        .and_then(|response| response.json::<Value>())
+        .map_err(|error| error.to_string())?;
+    let url = match gateway["url"].as_str() {
+        Some(url) => format!("{}/?v=10&encoding=json", url),
+        None => return Err("gateway URL missing".to_owned()),
+    };
+
+    // Connect, reading with a timeout so heartbeats go out on time
+    let (mut socket, _) = tungstenite::connect(url).map_err(|error| error.to_string())?;
+    set_read_timeout(&socket, Duration::from_secs(1)).map_err(|error| error.to_string())?;
+    let mut heartbeat_interval = None;
+    let mut last_heartbeat = Instant::now();
+    let mut sequence = Value::Null;
+
+    loop {
+        // Send heartbeat
+        if let Some(interval) = heartbeat_interval {
+            if last_heartbeat.elapsed() >= interval {
+                send(&mut socket, json!({"op": 1, "d": sequence}))?;
+                last_heartbeat = Instant::now();
+            }
+        }
+
+        let payload = match socket.read_message() {
+            Ok(Message::Text(text)) => match serde_json::from_str::<Value>(&text) {
+                Ok(payload) => payload,
+                Err(error) => return Err(error.to_string()),
+            },
+            Ok(Message::Close(frame)) => return Err(format!("closed by Discord ({:?})", frame)),
+            Ok(_) => continue,
+            Err(tungstenite::Error::Io(error))
+                if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) =>
+            {
+                continue
+            }
+            Err(error) => return Err(error.to_string()),
+        };
+        if !payload["s"].is_null() {
+            sequence = payload["s"].clone();
+        }
+
+        match payload["op"].as_u64() {
+            // Hello
+            Some(10) => {
+                let interval = payload["d"]["heartbeat_interval"].as_u64().unwrap_or(41250);
+                heartbeat_interval = Some(Duration::from_millis(interval));
+                send(
+                    &mut socket,
+                    json!({"op": 2, "d": {
+                        "token": config.token,
+                        "intents": 0,
+                        "properties": {"os": "linux", "browser": "launcher", "device": "launcher"}
+                    }}),
+                )?;
+            }
+            // Dispatch
+            Some(0) => match payload["t"].as_str() {
+                Some("READY") => println!("[INFO] Discord bot connected"),
+                Some("INTERACTION_CREATE") => {
+                    handle_interaction(client, config, &payload["d"], controls, status)
+                }
+                _ => (),
+            },
+            // Heartbeat request
+            Some(1) => {
+                send(&mut socket, json!({"op": 1, "d": sequence}))?;
+                last_heartbeat = Instant::now();
+            }
+            // Reconnect or invalid session
+            Some(7) | Some(9) => return Err("Discord asked to reconnect".to_owned()),
+            _ => (),
+        }
+    }
+}
+
+fn set_read_timeout(socket: &WebSocket<AutoStream>, timeout: Duration) -> std::io::Result<()> {
+    match socket.get_ref() {
+        Stream::Plain(stream) => stream.set_read_timeout(Some(timeout)),
+        Stream::Tls(stream) => stream.get_ref().set_read_timeout(Some(timeout)),
+    }
+}
+
+fn send(socket: &mut WebSocket<AutoStream>, payload: Value) -> Result<(), String> {
+    socket
+        .write_message(Message::Text(payload.to_string()))
+        .map_err(|error| error.to_string())
+}
+
+// Answer a slash command
+fn handle_interaction(
+    client: &reqwest::blocking::Client,
+    config: &BotConfig,
+    interaction: &Value,
+    controls: &Sender<Control>,
+    status: &SharedStatus,
+) {
+    // Only application commands
+    if interaction["type"].as_u64() != Some(2) {
+        return;
+    }
+    let command = interaction["data"]["name"].as_str().unwrap_or_default();
+    let user = interaction["member"]["user"]["username"]
+        .as_str()
+        .unwrap_or("unknown");
+    println!("[INFO] Discord user '{}' used /{}", user, command);
+
+    let content = run_command(config, interaction, controls, status);
+    let response = client
+        .post(format!(
+            "{}/interactions/{}/{}/callback",
+            config.api_url,
+            interaction["id"].as_str().unwrap_or_default(),
+            interaction["token"].as_str().unwrap_or_default()
+        ))
+        .json(&json!({"type": 4, "data": {"content": content}}))
+        .send();
+    match response {
+        Ok(response) if response.status().is_success() => (),
+        Ok(response) => println!(
+            "[WARN] Failed to answer Discord interaction: HTTP {}",
+            response.status()
+        ),
+        Err(error) => println!("[WARN] Failed to answer Discord interaction: {}", error),
+    }
+}
+
+// Run a slash command through the monitoring loop, returning the reply
+fn run_command(
+    config: &BotConfig,
+    interaction: &Value,
+    controls: &Sender<Control>,
+    status: &SharedStatus,
+) -> String {
+    let roles: Vec<&str> = interaction["member"]["roles"]
+        .as_array()
+        .map(|roles| roles.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let has_role = |allowed: &[String]| allowed.iter().any(|role| roles.contains(&role.as_str()));
+    let command = interaction["data"]["name"].as_str().unwrap_or_default();
+    let is_control = matches!(command, "extend" | "stop" | "start");
+    if is_control && !has_role(&config.control_roles)
+        || !is_control && !config.view_roles.is_empty() && !has_role(&config.view_roles)
+    {
+        return "You don't have permission to do that.".to_owned();
+    }
+
+    let status = status::snapshot(status);
+    let now = config.clock.now();
+    let request = |control| match controls.send(control) {
+        Ok(()) => true,
+        Err(_) => false,
+    };
+    match command {
+        "status" if status.running => {
+            let mut reply = format!(
+                "**{}** is running Minecraft {}",
+                status.level_name, status.server_version
+            );
+            if let Some(launch_time) = status.launch_time {
+                reply += &format!(", up for {}", control::format_duration(now - launch_time));
+            }
+            if let Some(scheduled_time) = status.scheduled_time {
+                reply += &format!(
+                    ", shutdown in {}",
+                    control::format_duration(scheduled_time - now)
+                );
+            }
+            match &status.server_status {
+                Some(server_status) => {
+                    reply
+                        + &format!(
+                            " ({}/{} players online).",
+                            server_status.online_players, server_status.max_players
+                        )
+                }
+                None => reply + &format!(" ({} players online).", status.online_players.len()),
+            }
+        }
+        "status" => match status.next_session {
+            Some(next_session) => format!("Server is offline until {}.", next_session),
+            None => "Server is offline.".to_owned(),
+        },
+        "players" if status.online_players.is_empty() => "Nobody is online.".to_owned(),
+        "players" => format!(
+            "Online players: {}",
+            status
+                .online_players
+                .iter()
+                .map(|player| format!("`{}`", player))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+        "extend" if !status.running => "Server is offline.".to_owned(),
+        "extend" => {
+            let duration = interaction["data"]["options"][0]["value"]
+                .as_str()
+                .and_then(control::parse_duration);
+            match duration {
+                Some(duration) if request(Control::Extend(duration)) => format!(
+                    "Extending the session by {}.",
+                    control::format_duration(duration)
+                ),
+                Some(_) => "Launcher is not accepting requests.".to_owned(),
+                None => "Invalid duration, try something like `30m` or `1h`.".to_owned(),
+            }
+        }
+        "stop" if !status.running => "Server is already offline.".to_owned(),
+        "stop" if request(Control::Stop) => "Stopping server...".to_owned(),
+        "start" if status.running => "Server is already running.".to_owned(),
+        "start" if request(Control::Start) => "Starting server...".to_owned(),
+        "stop" | "start" => "Launcher is not accepting requests.".to_owned(),
+        _ => "Unknown command.".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::status::Status;
+    use crate::stub::Stub;
+    use std::sync::mpsc;
+    use std::sync::{Arc, Mutex};
+
+    fn config(api_url: &str) -> BotConfig {
+        BotConfig {
+            token: "token".to_owned(),
+            application_id: "123".to_owned(),
+            guild_id: Some("456".to_owned()),
+            api_url: api_url.to_owned(),
+            control_roles: vec!["op".to_owned()],
+            view_roles: Vec::new(),
+            clock: Clock::default(),
+        }
+    }
+
+    #[test]
+    fn registers_guild_commands() {
+        let stub = Stub::start(vec![(200, Vec::new(), "[]".to_owned())]);
+        let client = reqwest::blocking::Client::new();
+        register_commands(&client, &config(&stub.url)).unwrap();
+
+        let request = stub.next_request();
+        assert_eq!(request.method, "PUT");
+        assert_eq!(request.path, "/applications/123/guilds/456/commands");
+        assert!(request
+            .headers
+            .contains(&("authorization".to_owned(), "Bot token".to_owned())));
+        let commands: Value = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(commands.as_array().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn answers_extend_through_the_control_path() {
+        let stub = Stub::start(vec![(204, Vec::new(), String::new())]);
+        let client = reqwest::blocking::Client::new();
+        let (sender, receiver) = mpsc::channel();
+        let status = Arc::new(Mutex::new(Status {
+            running: true,
+            ..Status::default()
+        }));
+        let interaction = json!({
+            "id": "1", "token": "abc", "type": 2,
+            "member": {"roles": ["op"], "user": {"username": "steve"}},
+            "data": {"name": "extend", "options": [{"name": "duration", "value": "30m"}]}
+        });
+        handle_interaction(&client, &config(&stub.url), &interaction, &sender, &status);
+
+        match receiver.try_recv() {
+            Ok(Control::Extend(duration)) => assert_eq!(duration, chrono::Duration::minutes(30)),
+            _ => panic!("extend request not sent"),
+        }
+        let request = stub.next_request();
+        assert_eq!(request.path, "/interactions/1/abc/callback");
+        assert!(request.body.contains("Extending the session by 0h 30m 0s."));
+    }
+
+    #[test]
+    fn refuses_control_commands_without_role() {
+        let (sender, receiver) = mpsc::channel();
+        let status = Arc::new(Mutex::new(Status {
+            running: true,
+            ..Status::default()
+        }));
+        let interaction = json!({
+            "type": 2,
+            "member": {"roles": ["player"]},
+            "data": {"name": "stop"}
+        });
+        let reply = run_command(&config(""), &interaction, &sender, &status);
+
+        assert_eq!(reply, "You don't have permission to do that.");
+        assert!(receiver.try_recv().is_err());
+    }
+}
@@ -0,0 +1,95 @@
+// Changes undone when the launcher is done with them, on errors and panics too
+use crate::context::Context;
+use crate::error::LauncherError;
+use crate::properties;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+use std::process::Command;
+
+// server.lock, so only one launcher runs the server, released when dropped
+pub struct ServerLock {
+    pub whoami: String,
+    path: PathBuf,
+}
+
+impl ServerLock {
+    pub fn acquire(context: &Context) -> Result<ServerLock, LauncherError> {
+        let path = context.path("server.lock");
+        let mut file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == ErrorKind::AlreadyExists => {
+                // Lock file contents say who holds it
+                let contents = match fs::read_to_string(&path) {
+                    Ok(contents) => contents.trim().to_owned(),
+                    Err(error) => {
+                        println!("[ERROR] Failed to read server.lock contents: '{}", error);
+                        String::new()
+                    }
+                };
+                return Err(LauncherError::Locked(contents));
+            }
+            Err(error) => return Err(LauncherError::Lock(error)),
+        };
+
+        // Check whoami
+        let whoami = match Command::new("whoami").output() {
+            Ok(output) => String::from_utf8_lossy(&output.stdout).trim().to_owned(),
+            Err(error) => {
+                println!("[WARN] Unable to call `whoami`: {}", error);
+                "unknown".to_owned()
+            }
+        };
+
+        // Write whoami to lock file, the lock is released if that fails
+        let lock = ServerLock { whoami, path };
+        file.write_all(lock.whoami.as_bytes())
+            .map_err(LauncherError::Lock)?;
+        println!("[INFO] server.lock file created");
+        Ok(lock)
+    }
+}
+
+impl Drop for ServerLock {
+    fn drop(&mut self) {
+        match fs::remove_file(&self.path) {
+            Ok(()) => println!("[INFO] server.lock file deleted"),
+            Err(error) => println!("[ERROR] Failed to delete server.lock file: {}", error),
+        }
+    }
+}
+
+// Puts the MOTD in server.properties back when dropped
+pub struct MotdGuard {
+    motd: Option<String>,
+    path: PathBuf,
+    temporary_path: PathBuf,
+}
+
+impl MotdGuard {
+    // Remember the MOTD in server.properties contents, before it's replaced
+    pub fn new(context: &Context, contents: &str) -> MotdGuard {
+        MotdGuard {
+            motd: properties::get(contents, "motd").map(str::to_owned),
+            path: context.path("server.properties"),
+            temporary_path: context.path("server.properties.tmp"),
+        }
+    }
+}
+
+impl Drop for MotdGuard {
+    fn drop(&mut self) {
+        let motd = match &self.motd {
+            Some(motd) => motd,
+            None => return,
+        };
+        let result = fs::read_to_string(&self.path)
+            .map(|contents| properties::set(&contents, "motd", motd))
+            .and_then(|contents| fs::write(&self.temporary_path, contents))
+            .and_then(|()| fs::rename(&self.temporary_path, &self.path));
+        match result {
+            Ok(()) => println!("[INFO] Server motd restored"),
+            Err(error) => println!("[ERROR] Failed to restore server motd: {}", error),
+        }
+    }
+}
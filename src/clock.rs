@@ -0,0 +1,54 @@
+// Launcher time, read by everything that runs on a schedule
+//
+// It's the system clock unless it's simulated, which tests do to run a whole
+// session with its reminders and shutdown in seconds.
+use chrono::prelude::{DateTime, Local};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Default)]
+pub struct Clock {
+    simulation: Option<Simulation>,
+}
+
+// Simulated time, `start` when the simulation began and `speed` times faster since
+#[derive(Clone, Copy)]
+struct Simulation {
+    start: DateTime<Local>,
+    started: Instant,
+    speed: u32,
+}
+
+impl Clock {
+    // Time from `start` on, passing `speed` times faster than it really does
+    pub fn simulated(start: DateTime<Local>, speed: u32) -> Clock {
+        Clock {
+            simulation: Some(Simulation {
+                start,
+                started: Instant::now(),
+                speed: speed.max(1),
+            }),
+        }
+    }
+
+    pub fn now(&self) -> DateTime<Local> {
+        match &self.simulation {
+            Some(simulation) => {
+                let elapsed = simulation.started.elapsed() * simulation.speed;
+                simulation.start
+                    + chrono::Duration::from_std(elapsed)
+                        .unwrap_or_else(|_| chrono::Duration::zero())
+            }
+            None => Local::now(),
+        }
+    }
+
+    // Sleep for a while of launcher time
+    pub fn sleep(&self, duration: Duration) {
+        let speed = self
+            .simulation
+            .as_ref()
+            .map_or(1, |simulation| simulation.speed);
+        thread::sleep(duration / speed);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::control::{self, Control};
+use std::io::{self, BufRead};
+use std::sync::mpsc::Sender;
+use std::thread;
+
+// Lines starting with this prefix are launcher commands instead of server commands
+const COMMAND_PREFIX: char = '!';
+
+// Read launcher stdin on a background thread
+pub fn spawn(sender: Sender<Control>) {
+    thread::spawn(move || read_stdin(sender));
+}
+
+// Forward stdin lines until stdin closes or the monitoring loop goes away
+fn read_stdin(sender: Sender<Control>) {
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(error) => {
+                println!("[WARN] Failed to read console input: {}", error);
+                break;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        if sender.send(parse_line(&line)).is_err() {
+            break;
+        }
+    }
+}
+
+// Turn a console line into a control request
+pub fn parse_line(line: &str) -> Control {
+    let line = line.trim();
+    if !line.starts_with(COMMAND_PREFIX) {
+        return Control::ServerCommand(line.to_owned());
+    }
+    let mut words = line[1..].split_whitespace();
+    match (words.next(), words.next()) {
+        (Some("status"), None) => Control::Status,
+        (Some("extend"), Some(amount)) => match control::parse_duration(amount) {
+            Some(duration) => Control::Extend(duration),
+            None => Control::Unknown(line.to_owned()),
+        },
+        (Some("backup"), None) => Control::Backup,
+        (Some("restart"), None) => Control::Restart,
+        (Some("stop"), None) => Control::Stop,
+        (Some("start"), None) => Control::Start,
+        (Some("reminders"), None) => Control::Reminders,
+        _ => Control::Unknown(line.to_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn parses_launcher_commands() {
+        assert_eq!(parse_line("  !status "), Control::Status);
+        assert_eq!(parse_line("!backup"), Control::Backup);
+        assert_eq!(parse_line("!restart"), Control::Restart);
+        assert_eq!(parse_line("!stop"), Control::Stop);
+        assert_eq!(parse_line("!start"), Control::Start);
+        assert_eq!(parse_line("!reminders"), Control::Reminders);
+        assert_eq!(
+            parse_line("!stop now"),
+            Control::Unknown("!stop now".to_owned())
+        );
+        assert_eq!(parse_line("!"), Control::Unknown("!".to_owned()));
+    }
+
+    #[test]
+    fn forwards_everything_else() {
+        assert_eq!(
+            parse_line("say hello !everyone\n"),
+            Control::ServerCommand("say hello !everyone".to_owned())
+        );
+        assert_eq!(
+            parse_line("stop"),
+            Control::ServerCommand("stop".to_owned())
+        );
+    }
+
+    #[test]
+    fn parses_extension_durations() {
+        assert_eq!(
+            parse_line("!extend 1h30m"),
+            Control::Extend(Duration::minutes(90))
+        );
+        assert_eq!(
+            parse_line("!extend 15"),
+            Control::Extend(Duration::minutes(15))
+        );
+        // Past the longest duration, or overflowing
+        for amount in &["400d", "9999999999h", "99999999999999999999m", "soon", ""] {
+            let line = format!("!extend {}", amount);
+            assert_eq!(parse_line(&line), Control::Unknown(line.trim().to_owned()));
+        }
+        // Parsed, the session refuses more than the cap
+        assert_eq!(parse_line("!extend 2d"), Control::Extend(Duration::days(2)));
+    }
+}
@@ -0,0 +1,43 @@
+// Where the server runs, what time it is and what runs it
+//
+// The launcher uses the working directory, the system clock and java. Tests
+// use a temporary directory, a simulated clock and a fake server instead.
+use crate::clock::Clock;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Clone)]
+pub struct Context {
+    // Server directory, every file the launcher reads and writes is in it
+    pub root: PathBuf,
+    pub clock: Clock,
+    // Program the server and the Java version check are run with
+    pub java: PathBuf,
+    // Open worlds saved by a newer version than the server jar, --force
+    pub force: bool,
+}
+
+impl Default for Context {
+    fn default() -> Context {
+        Context {
+            root: PathBuf::from("."),
+            clock: Clock::default(),
+            java: PathBuf::from("java"),
+            force: false,
+        }
+    }
+}
+
+impl Context {
+    // File in the server directory
+    pub fn path<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        self.root.join(path)
+    }
+
+    // java, or whatever stands in for it, run in the server directory
+    pub fn java(&self) -> Command {
+        let mut command = Command::new(&self.java);
+        command.current_dir(&self.root);
+        command
+    }
+}
@@ -0,0 +1,140 @@
+use chrono::prelude::{DateTime, Local};
+use chrono::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Set once the launcher receives SIGINT or SIGTERM
+static TERMINATE: AtomicBool = AtomicBool::new(false);
+
+// Requests handled by the monitoring loop
+#[derive(Debug, PartialEq)]
+pub enum Control {
+    // Raw command forwarded to the server console
+    ServerCommand(String),
+    // Print launcher status
+    Status,
+    // Push the scheduled shutdown back
+    Extend(Duration),
+    // Backup the world
+    Backup,
+    // Stop and relaunch the server
+    Restart,
+    // Stop the server
+    Stop,
+    // Start the server while it's offline
+    Start,
+    // Print the reminders state
+    Reminders,
+    // Launcher command that couldn't be understood
+    Unknown(String),
+}
+
+// Stop gracefully on SIGINT and SIGTERM instead of dying mid-session
+pub fn handle_signals() {
+    let result = ctrlc::set_handler(|| {
+        println!("[WARN] Received termination signal, stopping server");
+        TERMINATE.store(true, Ordering::SeqCst);
+    });
+    if let Err(error) = result {
+        println!("[WARN] Failed to set signal handler: {}", error);
+    }
+}
+
+// Check if the launcher was asked to terminate
+pub fn terminating() -> bool {
+    TERMINATE.load(Ordering::SeqCst)
+}
+
+// Longest a single extension can push the shutdown back
+const MAX_EXTENSION_HOURS: i64 = 24;
+
+// Longest duration accepted, anything longer is a typo and would overflow the clock
+const MAX_DURATION_SECONDS: i64 = 366 * 24 * 60 * 60;
+
+// Parse durations such as "30m", "1h", "1h30m" or "90s"
+pub fn parse_duration(input: &str) -> Option<Duration> {
+    let mut total: i64 = 0;
+    let mut number = String::new();
+    for character in input.trim().chars() {
+        if character.is_ascii_digit() {
+            number.push(character);
+            continue;
+        }
+        let value = number.parse::<i64>().ok()?;
+        number.clear();
+        let unit = match character {
+            'd' => 24 * 60 * 60,
+            'h' => 60 * 60,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        total = total.checked_add(value.checked_mul(unit)?)?;
+    }
+    if !number.is_empty() {
+        // Plain numbers are minutes
+        total = total.checked_add(number.parse::<i64>().ok()?.checked_mul(60)?)?;
+    }
+    if total <= 0 || total > MAX_DURATION_SECONDS {
+        return None;
+    }
+    Some(Duration::seconds(total))
+}
+
+// Shutdown time pushed back by an extension, or why it can't be
+pub fn extended(
+    scheduled_time: Option<DateTime<Local>>,
+    duration: Duration,
+) -> Result<DateTime<Local>, String> {
+    if duration > Duration::hours(MAX_EXTENSION_HOURS) {
+        return Err(format!(
+            "Can't extend by more than {} hours at once",
+            MAX_EXTENSION_HOURS
+        ));
+    }
+    scheduled_time
+        .ok_or_else(|| "No shutdown scheduled".to_owned())?
+        .checked_add_signed(duration)
+        .ok_or_else(|| "Can't extend the shutdown that far".to_owned())
+}
+
+// Format a duration as "1h 30m 0s"
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.num_seconds().max(0);
+    format!(
+        "{}h {}m {}s",
+        seconds / 3600,
+        (seconds % 3600) / 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("1h30m"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("45"), Some(Duration::minutes(45)));
+        assert_eq!(parse_duration("0m"), None);
+        assert_eq!(parse_duration("5x"), None);
+        assert_eq!(parse_duration("9999999999h"), None);
+        assert_eq!(parse_duration("99999999999999999999d"), None);
+        assert_eq!(parse_duration("400d"), None);
+    }
+
+    #[test]
+    fn caps_extensions() {
+        let scheduled_time = Local.ymd(2021, 5, 1).and_hms(22, 0, 0);
+        assert_eq!(
+            extended(Some(scheduled_time), Duration::hours(24)),
+            Ok(Local.ymd(2021, 5, 2).and_hms(22, 0, 0))
+        );
+        assert!(extended(Some(scheduled_time), Duration::hours(25)).is_err());
+        assert!(extended(None, Duration::minutes(30)).is_err());
+        // Past the end of time
+        let end = chrono::MAX_DATETIME.with_timezone(&Local) - Duration::minutes(10);
+        assert!(extended(Some(end), Duration::minutes(30)).is_err());
+    }
+}
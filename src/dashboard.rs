@@ -0,0 +1,401 @@
+// Web dashboard and REST API, served when dashboard-port is set in server.properties
+//
+// Requests need the token from dashboard.token or DASHBOARD_TOKEN, either as
+// "Authorization: Bearer <token>" or as ?token=<token> for the console stream, which
+// browsers can't add headers to.
+//
+//   GET  /                 dashboard page
+//   GET  /api/status       launcher and server state
+//   GET  /api/players      online players
+//   GET  /api/schedule     shutdown, restart and next session times
+//   GET  /api/backups      world backups in backups/
+//   GET  /api/console      server console as server-sent events
+//   POST /api/command      {"command": "say hi"}
+//   POST /api/extend       {"duration": "30m"}
+//   POST /api/backups      start a backup
+//   POST /api/restart, /api/stop, /api/start
+use crate::context::Context;
+use crate::control::{self, Control};
+use crate::output;
+use crate::properties;
+use crate::secrets::{self, Secret};
+use crate::status::{self, SharedStatus, Status};
+use chrono::prelude::{DateTime, Local};
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+const PAGE: &str = include_str!("../assets/dashboard.html");
+
+// Largest request body accepted
+const MAX_BODY_LENGTH: u64 = 64 * 1024;
+
+// How often the console stream looks for new lines
+const CONSOLE_POLL: Duration = Duration::from_millis(250);
+
+// Dashboard settings, from the dashboard token and server.properties
+pub struct DashboardConfig {
+    address: String,
+    port: u16,
+    token: String,
+    // Listed by /api/backups
+    backups_path: PathBuf,
+}
+
+impl DashboardConfig {
+    // Enabled by dashboard-port, only served locally unless dashboard-address says otherwise
+    pub fn load(context: &Context) -> Option<DashboardConfig> {
+        let contents = fs::read_to_string(context.path("server.properties")).unwrap_or_default();
+        properties::get(&contents, "dashboard-port")?;
+        let token = match secrets::get(context, Secret::DashboardToken) {
+            Some(token) => token,
+            None => {
+                println!("[WARN] Dashboard token missing or empty, dashboard disabled");
+                return None;
+            }
+        };
+        Some(DashboardConfig {
+            address: properties::get(&contents, "dashboard-address")
+                .unwrap_or("127.0.0.1")
+                .to_owned(),
+            port: properties::get_number(&contents, "dashboard-port", 8080),
+            token,
+            backups_path: context.path("backups"),
+        })
+    }
+}
+
+// Serve the dashboard on background threads
+pub fn spawn(config: DashboardConfig, controls: Sender<Control>, status: SharedStatus) {
+    let address = format!("{}:{}", config.address, config.port);
+    let server = match Server::http(&address) {
+        Ok(server) => server,
+        Err(error) => {
+            println!("[WARN] Failed to serve dashboard on {}: {}", address, error);
+            return;
+        }
+    };
+    println!("[INFO] Serving dashboard on http://{}/", address);
+    let config = Arc::new(config);
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            // Console streams stay open, so every request gets its own thread
+            let config = config.clone();
+            let controls = controls.clone();
+            let status = status.clone();
+            thread::spawn(move || handle_request(request, &config, &controls, &status));
+        }
+    });
+}
+
+fn handle_request(
+    mut request: Request,
+    config: &DashboardConfig,
+    controls: &Sender<Control>,
+    status: &SharedStatus,
+) {
+    let url = match reqwest::Url::parse(&format!("http://localhost{}", request.url())) {
+        Ok(url) => url,
+        Err(_) => return respond(request, 400, json!({"error": "Invalid URL"})),
+    };
+    let method = request.method().clone();
+
+    if method == Method::Get && url.path() == "/" {
+        let content_type = Header::from_bytes("Content-Type", "text/html; charset=utf-8").unwrap();
+        let _ = request.respond(Response::from_string(PAGE).with_header(content_type));
+        return;
+    }
+
+    let authorization = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Authorization"))
+        .map(|header| header.value.as_str().to_owned());
+    let query_token = url
+        .query_pairs()
+        .find(|(key, _)| key == "token")
+        .map(|(_, value)| value.into_owned());
+    if !authorized(config, authorization.as_deref(), query_token.as_deref()) {
+        return respond(request, 401, json!({"error": "Invalid or missing token"}));
+    }
+
+    if method == Method::Get && url.path() == "/api/console" {
+        return stream_console(request);
+    }
+
+    let mut body = String::new();
+    if request
+        .as_reader()
+        .take(MAX_BODY_LENGTH)
+        .read_to_string(&mut body)
+        .is_err()
+    {
+        return respond(request, 400, json!({"error": "Invalid body"}));
+    }
+    let body = if body.trim().is_empty() {
+        Value::Null
+    } else {
+        match serde_json::from_str(&body) {
+            Ok(body) => body,
+            Err(_) => return respond(request, 400, json!({"error": "Body is not JSON"})),
+        }
+    };
+
+    let (code, reply) = route(config, &method, url.path(), &body, controls, status);
+    respond(request, code, reply);
+}
+
+// Bearer token from the header, or the token query parameter
+fn authorized(
+    config: &DashboardConfig,
+    authorization: Option<&str>,
+    query_token: Option<&str>,
+) -> bool {
+    let token = authorization
+        .and_then(|authorization| authorization.strip_prefix("Bearer "))
+        .or(query_token);
+    match token {
+        Some(token) => constant_time_eq(token.trim().as_bytes(), config.token.as_bytes()),
+        None => false,
+    }
+}
+
+// Compare without leaking how much of the token matched
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+// Answer an API request with a status code and JSON
+fn route(
+    config: &DashboardConfig,
+    method: &Method,
+    path: &str,
+    body: &Value,
+    controls: &Sender<Control>,
+    status: &SharedStatus,
+) -> (u16, Value) {
+    let status = status::snapshot(status);
+    let request = |control| controls.send(control).is_ok();
+    let accepted = |message: &str| (202, json!({ "message": message }));
+    let refused = |code, error: &str| (code, json!({ "error": error }));
+
+    match (method, path) {
+        (Method::Get, "/api/status") => (200, status_json(&status)),
+        (Method::Get, "/api/players") => (200, json!({ "players": status.online_players })),
+        (Method::Get, "/api/schedule") => (
+            200,
+            json!({
+                "shutdown_time": time_json(status.scheduled_time),
+                "restart_time": time_json(status.restart_time),
+                "next_session": time_json(status.next_session),
+            }),
+        ),
+        (Method::Get, "/api/backups") => (200, json!({ "backups": backups(&config.backups_path) })),
+        (Method::Post, "/api/command") => match body["command"].as_str() {
+            _ if !status.running => refused(409, "Server is offline"),
+            Some(command) if !command.trim().is_empty() => {
+                if request(Control::ServerCommand(command.trim().to_owned())) {
+                    accepted("Command sent")
+                } else {
+                    refused(503, "Launcher is not accepting requests")
+                }
+            }
+            _ => refused(400, "Missing command"),
+        },
+        (Method::Post, "/api/extend") => {
+            match body["duration"].as_str().and_then(control::parse_duration) {
+                _ if !status.running => refused(409, "Server is offline"),
+                Some(duration) if request(Control::Extend(duration)) => accepted(&format!(
+                    "Extending the session by {}",
+                    control::format_duration(duration)
+                )),
+                Some(_) => refused(503, "Launcher is not accepting requests"),
+                None => refused(400, "Invalid duration, try something like 30m or 1h"),
+            }
+        }
+        (Method::Post, "/api/backups") if !status.running => refused(409, "Server is offline"),
+        (Method::Post, "/api/backups") if request(Control::Backup) => accepted("Backing up world"),
+        (Method::Post, "/api/restart") if !status.running => refused(409, "Server is offline"),
+        (Method::Post, "/api/restart") if request(Control::Restart) => {
+            accepted("Restarting server")
+        }
+        (Method::Post, "/api/stop") if !status.running => refused(409, "Server is already offline"),
+        (Method::Post, "/api/stop") if request(Control::Stop) => accepted("Stopping server"),
+        (Method::Post, "/api/start") if status.running => refused(409, "Server is already running"),
+        (Method::Post, "/api/start") if request(Control::Start) => accepted("Starting server"),
+        (Method::Post, "/api/backups" | "/api/restart" | "/api/stop" | "/api/start") => {
+            refused(503, "Launcher is not accepting requests")
+        }
+        _ => refused(404, "Not found"),
+    }
+}
+
+fn status_json(status: &Status) -> Value {
+    let server_status = status.server_status.as_ref();
+    json!({
+        "running": status.running,
+        "level_name": status.level_name,
+        "server_version": status.server_version,
+        "launch_time": time_json(status.launch_time),
+        "shutdown_time": time_json(status.scheduled_time),
+        "restart_time": time_json(status.restart_time),
+        "next_session": time_json(status.next_session),
+        "online_players": status.online_players.len(),
+        "max_players": server_status.map(|server_status| server_status.max_players),
+        "motd": server_status.map(|server_status| server_status.motd.clone()),
+        "latency_ms": server_status.map(|server_status| server_status.latency.as_millis() as u64),
+        "tps": status.tps,
+    })
+}
+
+fn time_json(time: Option<DateTime<Local>>) -> Value {
+    match time {
+        Some(time) => json!(time.to_rfc3339()),
+        None => Value::Null,
+    }
+}
+
+// Backups in the backups directory, newest first
+fn backups(path: &Path) -> Vec<Value> {
+    let mut backups = match fs::read_dir(path) {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let modified = entry.metadata().ok()?.modified().ok()?;
+                Some((entry.file_name().to_string_lossy().into_owned(), modified))
+            })
+            .collect::<Vec<_>>(),
+        Err(_) => Vec::new(),
+    };
+    backups.sort_by_key(|(_, modified)| std::cmp::Reverse(*modified));
+    backups
+        .into_iter()
+        .map(|(name, modified)| {
+            json!({
+                "name": name,
+                "created": DateTime::<Local>::from(modified).to_rfc3339(),
+            })
+        })
+        .collect()
+}
+
+fn respond(request: Request, code: u16, body: Value) {
+    let content_type = Header::from_bytes("Content-Type", "application/json").unwrap();
+    let response = Response::from_string(body.to_string())
+        .with_status_code(code)
+        .with_header(content_type);
+    let _ = request.respond(response);
+}
+
+// Send recent and new console lines until the browser goes away
+fn stream_console(request: Request) {
+    let mut writer = request.into_writer();
+    let headers = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n";
+    if writer.write_all(headers.as_bytes()).is_err() {
+        return;
+    }
+    let mut next = 0;
+    loop {
+        let (lines, following) = output::lines_since(next);
+        next = following;
+        let mut events = String::new();
+        for line in lines {
+            events += &format!("data: {}\n\n", line);
+        }
+        // Comments keep idle connections open and notice closed ones
+        if events.is_empty() {
+            events = ":\n\n".to_owned();
+        }
+        if writer.write_all(events.as_bytes()).is_err() || writer.flush().is_err() {
+            return;
+        }
+        thread::sleep(CONSOLE_POLL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::sync::Mutex;
+
+    fn config() -> DashboardConfig {
+        DashboardConfig {
+            address: "127.0.0.1".to_owned(),
+            port: 0,
+            token: "secret".to_owned(),
+            backups_path: PathBuf::from("backups"),
+        }
+    }
+
+    #[test]
+    fn checks_token_from_header_or_query() {
+        let config = config();
+        assert!(authorized(&config, Some("Bearer secret"), None));
+        assert!(authorized(&config, None, Some("secret")));
+        assert!(!authorized(&config, Some("Bearer secrets"), None));
+        assert!(!authorized(&config, Some("secret"), None));
+        assert!(!authorized(&config, None, None));
+    }
+
+    #[test]
+    fn sends_commands_through_the_control_path() {
+        let (sender, receiver) = mpsc::channel();
+        let status = Arc::new(Mutex::new(Status {
+            running: true,
+            ..Status::default()
+        }));
+
+        let (code, _) = route(
+            &config(),
+            &Method::Post,
+            "/api/command",
+            &json!({"command": "say hello"}),
+            &sender,
+            &status,
+        );
+        assert_eq!(code, 202);
+        match receiver.try_recv() {
+            Ok(Control::ServerCommand(command)) => assert_eq!(command, "say hello"),
+            _ => panic!("command not sent"),
+        }
+
+        let (code, reply) = route(
+            &config(),
+            &Method::Post,
+            "/api/extend",
+            &json!({"duration": "soon"}),
+            &sender,
+            &status,
+        );
+        assert_eq!(code, 400);
+        assert!(reply["error"].is_string());
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn refuses_to_start_a_running_server() {
+        let (sender, receiver) = mpsc::channel();
+        let status = Arc::new(Mutex::new(Status {
+            running: true,
+            ..Status::default()
+        }));
+
+        let (code, _) = route(
+            &config(),
+            &Method::Post,
+            "/api/start",
+            &Value::Null,
+            &sender,
+            &status,
+        );
+        assert_eq!(code, 409);
+        assert!(receiver.try_recv().is_err());
+    }
+}
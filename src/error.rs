@@ -0,0 +1,86 @@
+// Errors that end the launcher, each with its own exit code so scripts and
+// systemd can tell them apart:
+//
+//   0   success
+//   2   wrong command line usage
+//   3   server.properties missing, unreadable or incomplete
+//   4   Discord webhook URL invalid
+//   5   pre-flight checks failed
+//   6   server.lock held by another launcher
+//   7   server.lock couldn't be created or removed
+//   8   server jar missing or failing its checksum
+//   9   world saved by a newer Minecraft version than the jar
+//   10  server process couldn't be started
+//   11  server crashed
+//   12  world backup failed
+//   13  version upgrade failed
+//   14  history, playtime or world data couldn't be read or written
+//   101 launcher bug, a panic
+use std::fmt;
+use std::io;
+
+pub enum LauncherError {
+    Usage(String),
+    Config(String),
+    Webhook(String),
+    Preflight,
+    // Holds the lock file contents, who is running the server
+    Locked(String),
+    Lock(io::Error),
+    Jar(String),
+    Incompatible(String),
+    Launch(io::Error),
+    Crashed,
+    Backup(io::Error),
+    Upgrade(String),
+    Data(String),
+}
+
+impl LauncherError {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            LauncherError::Usage(_) => 2,
+            LauncherError::Config(_) => 3,
+            LauncherError::Webhook(_) => 4,
+            LauncherError::Preflight => 5,
+            LauncherError::Locked(_) => 6,
+            LauncherError::Lock(_) => 7,
+            LauncherError::Jar(_) => 8,
+            LauncherError::Incompatible(_) => 9,
+            LauncherError::Launch(_) => 10,
+            LauncherError::Crashed => 11,
+            LauncherError::Backup(_) => 12,
+            LauncherError::Upgrade(_) => 13,
+            LauncherError::Data(_) => 14,
+        }
+    }
+}
+
+impl fmt::Display for LauncherError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LauncherError::Usage(usage) => write!(formatter, "Usage: {}", usage),
+            LauncherError::Config(error) => write!(formatter, "{}", error),
+            LauncherError::Webhook(error) => write!(formatter, "{}", error),
+            LauncherError::Preflight => {
+                write!(
+                    formatter,
+                    "Pre-flight checks failed, not launching the server"
+                )
+            }
+            LauncherError::Locked(whoami) => write!(
+                formatter,
+                "Server is currently being run by '{}' or shutdown did not clear server.lock",
+                whoami
+            ),
+            LauncherError::Lock(error) => write!(formatter, "server.lock error: {}", error),
+            LauncherError::Jar(error) => write!(formatter, "{}", error),
+            LauncherError::Incompatible(error) => write!(formatter, "{}", error),
+            LauncherError::Launch(error) => write!(formatter, "Running process error: {}", error),
+            LauncherError::Crashed => write!(formatter, "Server has crashed"),
+            LauncherError::Backup(error) => write!(formatter, "Backup failed: {}", error),
+            LauncherError::Upgrade(error) => write!(formatter, "Upgrade failed: {}", error),
+            LauncherError::Data(error) => write!(formatter, "{}", error),
+        }
+    }
+}
@@ -0,0 +1,344 @@
+// Session history, one JSON object per session in history.jsonl
+use crate::context::Context;
+use crate::control;
+use crate::error::LauncherError;
+use crate::report::{SessionEvent, SessionReport, ShutdownReason};
+use chrono::prelude::{DateTime, Local};
+use serde_json::{json, Value};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+
+const HISTORY_PATH: &str = "history.jsonl";
+
+const CSV_COLUMNS: [&str; 14] = [
+    "start_time",
+    "end_time",
+    "duration_seconds",
+    "launched_by",
+    "level_name",
+    "server_version",
+    "scheduled_time",
+    "reason",
+    "exit_status",
+    "peak_players",
+    "players",
+    "joins",
+    "restarts",
+    "backups",
+];
+
+// Append a finished session to the history
+pub fn record(context: &Context, report: &SessionReport, launched_by: &str) {
+    let entry = entry(report, launched_by);
+    let result = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(context.path(HISTORY_PATH))
+        .and_then(|mut file| writeln!(file, "{}", entry));
+    match result {
+        Ok(()) => println!("[INFO] Session added to {}", HISTORY_PATH),
+        Err(error) => println!(
+            "[WARN] Failed to add session to {}: {}",
+            HISTORY_PATH, error
+        ),
+    }
+}
+
+fn entry(report: &SessionReport, launched_by: &str) -> Value {
+    let events = report
+        .events
+        .iter()
+        .map(|(time, event)| {
+            let mut entry = match event {
+                SessionEvent::Joined(player) => json!({"type": "joined", "player": player}),
+                SessionEvent::Left(player) => json!({"type": "left", "player": player}),
+                SessionEvent::Backup(Ok(path)) => {
+                    json!({"type": "backup", "path": path.display().to_string()})
+                }
+                SessionEvent::Backup(Err(error)) => json!({"type": "backup", "error": error}),
+                SessionEvent::Restarted(reason) => json!({"type": "restart", "reason": reason}),
+            };
+            entry["time"] = json!(time.to_rfc3339());
+            entry
+        })
+        .collect::<Vec<Value>>();
+    json!({
+        "launched_by": launched_by,
+        "level_name": report.level_name,
+        "server_version": report.server_version,
+        "start_time": report.start_time.to_rfc3339(),
+        "end_time": report.end_time.to_rfc3339(),
+        "scheduled_time": report.scheduled_time.map(|time| time.to_rfc3339()),
+        "reason": report.reason.name(),
+        "exit_status": report.exit_status(),
+        "exit_code": report.exit_status.and_then(|status| status.code()),
+        "peak_players": report.peak_players,
+        "players": report.joined_players,
+        "events": events,
+    })
+}
+
+// Every recorded session, oldest first
+fn load(context: &Context) -> Result<Vec<Value>, String> {
+    let contents = match fs::read_to_string(context.path(HISTORY_PATH)) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(format!("Failed to read {}: {}", HISTORY_PATH, error)),
+    };
+    Ok(contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(number, line)| match serde_json::from_str(line) {
+            Ok(entry) => Some(entry),
+            // On stderr, so it stays out of CSV printed to the console
+            Err(error) => {
+                eprintln!(
+                    "[WARN] Skipping line {} of {}: {}",
+                    number + 1,
+                    HISTORY_PATH,
+                    error
+                );
+                None
+            }
+        })
+        .collect())
+}
+
+// `history [--events] [--csv <file>]`, print the history or export it
+pub fn run(context: &Context, args: &[String]) -> Result<(), LauncherError> {
+    let usage = "history [--events] [--csv <file>], --csv - prints to the console";
+    let mut events = false;
+    let mut csv = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--events" => events = true,
+            "--csv" => match args.next() {
+                Some(path) => csv = Some(path),
+                None => return Err(LauncherError::Usage(usage.to_owned())),
+            },
+            _ => return Err(LauncherError::Usage(usage.to_owned())),
+        }
+    }
+    let entries = load(context).map_err(LauncherError::Data)?;
+
+    match csv {
+        Some(path) if path == "-" => print!("{}", to_csv(&entries)),
+        Some(path) => match fs::write(path, to_csv(&entries)) {
+            Ok(()) => println!("[INFO] Exported {} sessions to {}", entries.len(), path),
+            Err(error) => {
+                return Err(LauncherError::Data(format!(
+                    "Failed to write {}: {}",
+                    path, error
+                )))
+            }
+        },
+        None if entries.is_empty() => println!("[INFO] No sessions recorded yet"),
+        None => entries.iter().for_each(|entry| print_entry(entry, events)),
+    }
+    Ok(())
+}
+
+fn print_entry(entry: &Value, events: bool) {
+    let text = |key: &str| entry[key].as_str().unwrap_or("?").to_owned();
+    let start_time = time(&entry["start_time"]);
+    let end_time = time(&entry["end_time"]);
+    let duration = match (start_time, end_time) {
+        (Some(start_time), Some(end_time)) => control::format_duration(end_time - start_time),
+        _ => "?".to_owned(),
+    };
+    println!(
+        "{} to {} ({}), '{}' on Minecraft {}, launched by {}",
+        text("start_time"),
+        text("end_time"),
+        duration,
+        text("level_name"),
+        text("server_version"),
+        text("launched_by")
+    );
+
+    let reason = entry["reason"].as_str().unwrap_or("?");
+    let reason = ShutdownReason::parse(reason)
+        .map(ShutdownReason::label)
+        .unwrap_or_else(|| reason.to_owned());
+    match entry["scheduled_time"].as_str() {
+        Some(scheduled_time) => println!(
+            "  Ended: {} ({}), shutdown was scheduled for {}",
+            reason,
+            text("exit_status"),
+            scheduled_time
+        ),
+        None => println!("  Ended: {} ({})", reason, text("exit_status")),
+    }
+
+    let players = strings(&entry["players"]);
+    if players.is_empty() {
+        println!("  Players: nobody");
+    } else {
+        println!(
+            "  Players: {} (peak {})",
+            players.join(", "),
+            entry["peak_players"]
+        );
+    }
+    let entry_events = entry["events"].as_array().cloned().unwrap_or_default();
+    let count = |kind: &str| {
+        entry_events
+            .iter()
+            .filter(|event| event["type"] == kind)
+            .count()
+    };
+    if count("restart") > 0 || count("backup") > 0 {
+        println!(
+            "  Restarts: {}, backups: {}",
+            count("restart"),
+            count("backup")
+        );
+    }
+
+    if events {
+        for event in &entry_events {
+            let detail = match event["type"].as_str().unwrap_or_default() {
+                "joined" => format!("{} joined", event["player"].as_str().unwrap_or("?")),
+                "left" => format!("{} left", event["player"].as_str().unwrap_or("?")),
+                "backup" => match event["path"].as_str() {
+                    Some(path) => format!("Backup saved to {}", path),
+                    None => format!("Backup failed: {}", event["error"].as_str().unwrap_or("?")),
+                },
+                "restart" => format!("Restarted: {}", event["reason"].as_str().unwrap_or("?")),
+                other => other.to_owned(),
+            };
+            println!("  {} {}", event["time"].as_str().unwrap_or("?"), detail);
+        }
+    }
+    println!();
+}
+
+// One row per session
+fn to_csv(entries: &[Value]) -> String {
+    let mut csv = CSV_COLUMNS.join(",") + "\n";
+    for entry in entries {
+        let events = entry["events"].as_array().cloned().unwrap_or_default();
+        let of_type = |kind: &str| {
+            events
+                .iter()
+                .filter(|event| event["type"] == kind)
+                .cloned()
+                .collect::<Vec<Value>>()
+        };
+        let duration = match (time(&entry["start_time"]), time(&entry["end_time"])) {
+            (Some(start_time), Some(end_time)) => (end_time - start_time).num_seconds().to_string(),
+            _ => String::new(),
+        };
+        let backups = of_type("backup")
+            .iter()
+            .filter_map(|backup| backup["path"].as_str().map(str::to_owned))
+            .collect::<Vec<String>>();
+        let row = [
+            value(&entry["start_time"]),
+            value(&entry["end_time"]),
+            duration,
+            value(&entry["launched_by"]),
+            value(&entry["level_name"]),
+            value(&entry["server_version"]),
+            value(&entry["scheduled_time"]),
+            value(&entry["reason"]),
+            value(&entry["exit_status"]),
+            value(&entry["peak_players"]),
+            strings(&entry["players"]).join(";"),
+            of_type("joined").len().to_string(),
+            of_type("restart").len().to_string(),
+            backups.join(";"),
+        ];
+        csv += &row
+            .iter()
+            .map(|field| escape(field))
+            .collect::<Vec<String>>()
+            .join(",");
+        csv += "\n";
+    }
+    csv
+}
+
+// Quote fields with separators, quotes or line breaks
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+fn value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn strings(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .map(|values| {
+            values
+                .iter()
+                .filter_map(|value| value.as_str().map(str::to_owned))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn time(value: &Value) -> Option<DateTime<Local>> {
+    DateTime::parse_from_rfc3339(value.as_str()?)
+        .ok()
+        .map(|time| time.with_timezone(&Local))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use std::path::PathBuf;
+
+    #[test]
+    fn exports_recorded_sessions_to_csv() {
+        let start_time = Local::now();
+        let report = SessionReport {
+            level_name: "world".to_owned(),
+            world_path: PathBuf::from("world"),
+            server_version: "1.16.5".to_owned(),
+            start_time,
+            end_time: start_time + Duration::hours(2),
+            scheduled_time: Some(start_time + Duration::hours(2)),
+            reason: ShutdownReason::Scheduled,
+            exit_status: None,
+            peak_players: 2,
+            joined_players: vec!["Steve".to_owned(), "Alex".to_owned()],
+            events: vec![
+                (start_time, SessionEvent::Joined("Steve".to_owned())),
+                (start_time, SessionEvent::Joined("Alex".to_owned())),
+                (
+                    start_time,
+                    SessionEvent::Backup(Ok(PathBuf::from("./backups/world, old"))),
+                ),
+                (
+                    start_time,
+                    SessionEvent::Restarted("Daily restart".to_owned()),
+                ),
+            ],
+            play_intervals: Vec::new(),
+        };
+        // Entries go through the same text form as the history file
+        let entry = serde_json::from_str(&entry(&report, "alice").to_string()).unwrap();
+        let csv = to_csv(&[entry]);
+        let mut lines = csv.lines();
+
+        assert_eq!(lines.next().unwrap(), CSV_COLUMNS.join(","));
+        let row = lines.next().unwrap();
+        assert!(row.contains(",7200,alice,world,1.16.5,"));
+        assert!(row.ends_with(",scheduled,unknown,2,Steve;Alex,2,1,\"./backups/world, old\""));
+        assert!(lines.next().is_none());
+    }
+}
@@ -0,0 +1,255 @@
+// Server jars, checked against ./jars/checksums.json before being put in place
+//
+// The manifest maps versions to SHA-1 checksums, as listed in Mojang's version
+// manifest, such as {"1.16.5": "1b557e7b033b583cd9f66746b7a9ab1ec1673ced"}, or to
+// SHA-256 checksums. Versions missing from it are refused until added by hand or
+// with `jar trust <version>`.
+use crate::context::Context;
+use crate::error::LauncherError;
+use serde_json::{json, Value};
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+use std::fs::{self, File};
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+const MANIFEST_PATH: &str = "jars/checksums.json";
+const MANIFEST_TEMPORARY_PATH: &str = "jars/checksums.json.tmp";
+const SERVER_JAR_PATH: &str = "server.jar";
+const SERVER_JAR_TEMPORARY_PATH: &str = "server.jar.tmp";
+// The jar that was replaced, put back if the new one fails to launch
+const SERVER_JAR_PREVIOUS_PATH: &str = "server.jar.previous";
+
+// Put ./jars/<version>.jar in place as server.jar, unless it's already there
+pub fn install(context: &Context, version: &str) -> Result<(), String> {
+    let jar_path = context.path(format!("jars/{}.jar", version));
+    let checksum = verify(context, version, &jar_path)?;
+    match hash(&context.path(SERVER_JAR_PATH), &checksum) {
+        Ok(current) if current == checksum => {
+            println!("[INFO] server.jar is already Minecraft {}", version);
+            // Nothing to go back to if this jar fails
+            let _ = fs::remove_file(context.path(SERVER_JAR_PREVIOUS_PATH));
+            return Ok(());
+        }
+        Ok(_) | Err(_) => (),
+    }
+
+    // Copy next to server.jar first so it's only replaced once complete
+    fs::copy(&jar_path, context.path(SERVER_JAR_TEMPORARY_PATH))
+        .map_err(|error| format!("Failed to copy {}: {}", jar_path.display(), error))?;
+    match fs::rename(
+        context.path(SERVER_JAR_PATH),
+        context.path(SERVER_JAR_PREVIOUS_PATH),
+    ) {
+        Ok(()) => (),
+        Err(error) if error.kind() == ErrorKind::NotFound => (),
+        Err(error) => return Err(format!("Failed to keep the previous server.jar: {}", error)),
+    }
+    fs::rename(
+        context.path(SERVER_JAR_TEMPORARY_PATH),
+        context.path(SERVER_JAR_PATH),
+    )
+    .map_err(|error| format!("Failed to replace server.jar: {}", error))?;
+    println!("[INFO] server.jar replaced with {}", jar_path.display());
+    Ok(())
+}
+
+// Put back the server.jar replaced by the last install
+pub fn restore(context: &Context) {
+    match fs::rename(
+        context.path(SERVER_JAR_PREVIOUS_PATH),
+        context.path(SERVER_JAR_PATH),
+    ) {
+        Ok(()) => println!("[WARN] Restored the previous server.jar"),
+        Err(error) if error.kind() == ErrorKind::NotFound => (),
+        Err(error) => println!(
+            "[ERROR] Failed to restore the previous server.jar: {}",
+            error
+        ),
+    }
+}
+
+// `jar trust <version>`, add the SHA-256 of ./jars/<version>.jar to the manifest
+pub fn run(context: &Context, args: &[String]) -> Result<(), LauncherError> {
+    let version = match args {
+        [command, version] if command == "trust" => version,
+        _ => return Err(LauncherError::Usage("jar trust <version>".to_owned())),
+    };
+    let jar_path = context.path(format!("jars/{}.jar", version));
+    let checksum = hex::<Sha256>(&jar_path).map_err(|error| {
+        LauncherError::Jar(format!("Failed to read {}: {}", jar_path.display(), error))
+    })?;
+    let mut manifest = load_manifest(context).map_err(LauncherError::Jar)?;
+    if let Some(expected) = manifest.get(version.as_str()).and_then(Value::as_str) {
+        return Err(LauncherError::Jar(format!(
+            "{} already lists Minecraft {} with checksum {}, edit it by hand to replace it",
+            MANIFEST_PATH, version, expected
+        )));
+    }
+
+    manifest.insert(version.to_owned(), json!(checksum));
+    let manifest_path = context.path(MANIFEST_PATH);
+    let temporary_path = context.path(MANIFEST_TEMPORARY_PATH);
+    fs::write(
+        &temporary_path,
+        serde_json::to_string_pretty(&manifest).unwrap_or_default(),
+    )
+    .and_then(|()| fs::rename(&temporary_path, &manifest_path))
+    .map_err(|error| LauncherError::Jar(format!("Failed to save {}: {}", MANIFEST_PATH, error)))?;
+    println!(
+        "[INFO] Added Minecraft {} to {} with SHA-256 {}",
+        version, MANIFEST_PATH, checksum
+    );
+    Ok(())
+}
+
+// Compare a jar against the manifest, returning its checksum
+fn verify(context: &Context, version: &str, path: &Path) -> Result<String, String> {
+    let manifest = load_manifest(context)?;
+    let expected = match manifest.get(version).and_then(Value::as_str) {
+        Some(expected) => expected,
+        None => {
+            return Err(format!(
+                "Minecraft {} is missing from {}, check {} and run `jar trust {}` to add it",
+                version,
+                MANIFEST_PATH,
+                path.display(),
+                version
+            ))
+        }
+    };
+    let checksum = hash(path, expected)
+        .map_err(|error| format!("Failed to read {}: {}", path.display(), error))?;
+    if expected.eq_ignore_ascii_case(&checksum) {
+        Ok(checksum)
+    } else {
+        Err(format!(
+            "{} doesn't match {}, expected {} but found {}",
+            path.display(),
+            MANIFEST_PATH,
+            expected,
+            checksum
+        ))
+    }
+}
+
+// Versions and their checksums, empty if there's no manifest yet
+fn load_manifest(context: &Context) -> Result<serde_json::Map<String, Value>, String> {
+    match fs::read_to_string(context.path(MANIFEST_PATH)) {
+        Ok(contents) => match serde_json::from_str(&contents) {
+            Ok(Value::Object(manifest)) => Ok(manifest),
+            Ok(_) => Err(format!("{} is not a JSON object", MANIFEST_PATH)),
+            Err(error) => Err(format!("Failed to parse {}: {}", MANIFEST_PATH, error)),
+        },
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(serde_json::Map::new()),
+        Err(error) => Err(format!("Failed to read {}: {}", MANIFEST_PATH, error)),
+    }
+}
+
+// Hex encoded checksum of a file, SHA-256 or SHA-1 like the expected one
+fn hash(path: &Path, expected: &str) -> io::Result<String> {
+    if expected.len() == 64 {
+        hex::<Sha256>(path)
+    } else {
+        hex::<Sha1>(path)
+    }
+}
+
+fn hex<D: Digest + io::Write>(path: &Path) -> io::Result<String> {
+    let mut hasher = D::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    // SHA-1 and SHA-256 of "new jar"
+    const NEW_SHA1: &str = "46511d7e7815a1cb36c136400bee5b5c1f2f4867";
+    const NEW_SHA256: &str = "93eac758088da2129f1326ec632c69d7ea97040448abd60ca5218a1a6ff0a73a";
+
+    // Server directory with jars/1.17.jar and the given manifest, if any
+    fn server(name: &str, manifest: Option<&str>) -> Context {
+        let root = env::temp_dir().join(format!("launcher-jar-{}-{}", process::id(), name));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("jars")).unwrap();
+        fs::write(root.join("jars/1.17.jar"), "new jar").unwrap();
+        fs::write(root.join(SERVER_JAR_PATH), "old jar").unwrap();
+        if let Some(manifest) = manifest {
+            fs::write(root.join(MANIFEST_PATH), manifest).unwrap();
+        }
+        Context {
+            root,
+            ..Context::default()
+        }
+    }
+
+    fn server_jar(context: &Context) -> String {
+        fs::read_to_string(context.path(SERVER_JAR_PATH)).unwrap()
+    }
+
+    #[test]
+    fn installs_jars_matching_either_checksum() {
+        for (name, checksum) in [("sha1", NEW_SHA1), ("sha256", NEW_SHA256)].iter() {
+            let context = server(name, Some(&format!(r#"{{"1.17": "{}"}}"#, checksum)));
+            assert_eq!(install(&context, "1.17"), Ok(()));
+            assert_eq!(server_jar(&context), "new jar");
+
+            // Already in place, the jar that was there before is forgotten
+            assert_eq!(install(&context, "1.17"), Ok(()));
+            assert!(!context.path(SERVER_JAR_PREVIOUS_PATH).exists());
+            let _ = fs::remove_dir_all(&context.root);
+        }
+    }
+
+    #[test]
+    fn refuses_mismatched_and_unknown_jars() {
+        let context = server(
+            "mismatch",
+            Some(r#"{"1.17": "0000000000000000000000000000000000000000"}"#),
+        );
+        assert!(install(&context, "1.17")
+            .unwrap_err()
+            .contains("doesn't match"));
+        assert_eq!(server_jar(&context), "old jar");
+        let _ = fs::remove_dir_all(&context.root);
+
+        // Never checked until someone trusts it
+        let context = server("unknown", None);
+        assert!(install(&context, "1.17")
+            .unwrap_err()
+            .contains("jar trust 1.17"));
+        assert_eq!(server_jar(&context), "old jar");
+        let trust = ["trust".to_owned(), "1.17".to_owned()];
+        assert!(run(&context, &trust).is_ok());
+        assert_eq!(
+            fs::read_to_string(context.path(MANIFEST_PATH)).unwrap(),
+            format!("{{\n  \"1.17\": \"{}\"\n}}", NEW_SHA256)
+        );
+        // Trusted once, a different jar isn't trusted over it
+        assert!(run(&context, &trust).is_err());
+        assert_eq!(install(&context, "1.17"), Ok(()));
+        let _ = fs::remove_dir_all(&context.root);
+    }
+
+    #[test]
+    fn restores_the_previous_jar() {
+        let context = server("restore", Some(&format!(r#"{{"1.17": "{}"}}"#, NEW_SHA1)));
+        assert_eq!(install(&context, "1.17"), Ok(()));
+        restore(&context);
+        assert_eq!(server_jar(&context), "old jar");
+        assert!(!context.path(SERVER_JAR_PREVIOUS_PATH).exists());
+
+        // Nothing left to restore
+        restore(&context);
+        assert_eq!(server_jar(&context), "old jar");
+        let _ = fs::remove_dir_all(&context.root);
+    }
+}
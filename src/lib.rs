@@ -0,0 +1,543 @@
+// The launcher, main.rs only turns the outcome into an exit code
+//
+// Sessions can be run from here with any Context, the integration tests run them
+// in a temporary directory with a simulated clock, a stand-in for java and the
+// webhook stub.
+use chrono::prelude::{DateTime, Local, NaiveTime, TimeZone};
+use chrono::Duration;
+use regex::{NoExpand, Regex};
+use std::env;
+use std::fs;
+use std::io::{self, stdout, ErrorKind, Write};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+mod backup;
+mod bot;
+pub mod cleanup;
+pub mod clock;
+mod console;
+pub mod context;
+pub mod control;
+mod dashboard;
+pub mod error;
+mod history;
+mod jar;
+mod messages;
+mod metrics;
+mod nbt;
+pub mod notify;
+mod output;
+mod ping;
+mod playtime;
+mod preflight;
+pub mod properties;
+mod query;
+mod rcon;
+pub mod report;
+mod restart;
+pub mod schedule;
+mod secrets;
+pub mod server;
+pub mod session;
+pub mod status;
+// Shared with the integration tests, which use more of it
+#[cfg(test)]
+#[allow(dead_code)]
+mod stub;
+mod upgrade;
+mod vote;
+mod watchdog;
+mod webhook;
+pub mod world;
+
+use cleanup::{MotdGuard, ServerLock};
+use context::Context;
+use control::Control;
+use error::LauncherError;
+use notify::{Event, Notifiers};
+use report::ShutdownReason;
+use schedule::Schedule;
+use secrets::Secret;
+use session::Session;
+use status::SharedStatus;
+use world::WorldInfo;
+
+// Command run instead of the server, such as `history`, with the arguments after its name
+type Subcommand = fn(&Context, &[String]) -> Result<(), LauncherError>;
+
+// Options of the launcher itself, given anywhere on the command line and not passed to subcommands
+const LAUNCHER_FLAGS: [&str; 3] = ["--no-notify", "--schedule", "--no-shutdown"];
+
+pub fn run() -> Result<(), LauncherError> {
+    let app_name = "Minecraft Smart Server Launching Thingy";
+
+    // The subcommand is the first argument that isn't an option, it gets the others
+    let mut args = env::args().skip(1).collect::<Vec<String>>();
+    let flag = |name: &str| args.iter().any(|arg| arg == name);
+    let (no_notify, schedule, no_shutdown) = (
+        flag("--no-notify"),
+        flag("--schedule"),
+        flag("--no-shutdown"),
+    );
+    let command = args
+        .iter()
+        .position(|arg| !arg.starts_with("--"))
+        .map(|index| args.remove(index));
+    args.retain(|arg| !LAUNCHER_FLAGS.contains(&arg.as_str()));
+    let context = Context {
+        force: args.iter().any(|arg| arg == "--force"),
+        ..Context::default()
+    };
+
+    // Subcommands that don't run the server, their output may be piped elsewhere
+    let subcommand: Option<Subcommand> = match command.as_deref() {
+        Some("history") => Some(history::run),
+        Some("jar") => Some(jar::run),
+        Some("playtime") => Some(playtime::run),
+        Some("world") => Some(world::run),
+        _ => None,
+    };
+    if let Some(subcommand) = subcommand {
+        return subcommand(&context, &args);
+    }
+
+    // Print app name
+    println!("{}\n\n", app_name.to_uppercase());
+
+    // Pick the message locale
+    messages::load(&context);
+
+    // Notifications are optional, --no-notify turns them all off
+    let notifiers = if no_notify {
+        println!("[INFO] Notifications disabled");
+        Notifiers::none()
+    } else {
+        // Get Discord webhook URL
+        let discord_webhook_url = secrets::get(&context, Secret::DiscordWebhook);
+        match &discord_webhook_url {
+            Some(url) => match notify::check_webhook_url(url) {
+                Ok(()) => println!(
+                    "[INFO] Discord webhook URL: '{}'",
+                    notify::redact_webhook_url(url)
+                ),
+                Err(error) => {
+                    return Err(LauncherError::Webhook(format!(
+                        "Invalid Discord webhook URL '{}': {}",
+                        notify::redact_webhook_url(url),
+                        error
+                    )))
+                }
+            },
+            None => println!("[INFO] No Discord webhook URL set, not posting to Discord"),
+        }
+        Notifiers::load(&context, app_name, discord_webhook_url.as_deref())
+    };
+
+    // Subcommands that report to the notifiers
+    if command.as_deref() == Some("upgrade") {
+        return upgrade::run(&context, &notifiers, &args);
+    }
+
+    // Asked before the console starts reading input
+    preflight::offer_eula(&context);
+
+    // Requests from the launcher console and the Discord bot
+    let (control_sender, controls) = mpsc::channel();
+    let status = SharedStatus::default();
+
+    if schedule {
+        // Long-running mode, sessions follow the schedule in server.properties
+        control::handle_signals();
+        spawn_control_threads(&context, control_sender, &status);
+        run_schedule(&context, &notifiers, &controls, &status)
+    } else if no_shutdown {
+        // Long-running mode, the server only goes down for restarts
+        control::handle_signals();
+        spawn_control_threads(&context, control_sender, &status);
+        run_session(&context, &notifiers, None, &controls, &status)
+    } else {
+        // Single session, shutdown time is asked for
+        let scheduled_time = read_scheduled_time(&context.clock);
+        control::handle_signals();
+        spawn_control_threads(&context, control_sender, &status);
+        run_session(
+            &context,
+            &notifiers,
+            Some(scheduled_time),
+            &controls,
+            &status,
+        )
+    }
+}
+
+// Start reading the launcher console, and the Discord bot, dashboard and metrics when configured
+fn spawn_control_threads(
+    context: &Context,
+    control_sender: Sender<Control>,
+    status: &SharedStatus,
+) {
+    console::spawn(control_sender.clone());
+    if let Some(config) = dashboard::DashboardConfig::load(context) {
+        dashboard::spawn(config, control_sender.clone(), status.clone());
+    }
+    if let Some(config) = bot::BotConfig::load(context) {
+        bot::spawn(config, control_sender, status.clone());
+    }
+    if let Some(config) = metrics::MetricsConfig::load(context) {
+        metrics::spawn(config, status.clone());
+    }
+}
+
+// Run sessions during the schedule windows, sleeping in between
+pub fn run_schedule(
+    context: &Context,
+    notifiers: &Notifiers,
+    controls: &Receiver<Control>,
+    status: &SharedStatus,
+) -> Result<(), LauncherError> {
+    // Read schedule from server.properties
+    let contents = match fs::read_to_string(context.path("server.properties")) {
+        Ok(contents) => contents,
+        Err(error) => {
+            return Err(LauncherError::Config(format!(
+                "Failed to open server.properties: {}",
+                error
+            )))
+        }
+    };
+    let schedule = match properties::get(&contents, "session-schedule") {
+        Some(definition) => match Schedule::parse(definition) {
+            Ok(schedule) => schedule,
+            Err(error) => {
+                return Err(LauncherError::Config(format!(
+                    "Invalid session-schedule: {}",
+                    error
+                )))
+            }
+        },
+        None => {
+            return Err(LauncherError::Config(
+                "session-schedule missing from server.properties!".to_owned(),
+            ))
+        }
+    };
+
+    while !control::terminating() {
+        let now = context.clock.now();
+        match schedule.window_at(now) {
+            Some(window) => {
+                println!(
+                    "[INFO] Session window open from {} to {}",
+                    window.start, window.end
+                );
+                skip_failed_session(run_session(
+                    context,
+                    notifiers,
+                    Some(window.end),
+                    controls,
+                    status,
+                ))?;
+
+                // Don't relaunch a server that stopped early until the window closes
+                // A start request relaunches it for the rest of the window
+                sleep_until(context, window.end, controls, status);
+            }
+            None => match schedule.next_window(now) {
+                Some(window) => {
+                    println!(
+                        "[INFO] Next session from {} to {}",
+                        window.start, window.end
+                    );
+                    notifiers.notify(&Event::NextSession {
+                        start_time: window.start,
+                        shutdown_time: window.end,
+                    });
+                    if sleep_until(context, window.start, controls, status) {
+                        // Started on request, run until the configured session length
+                        let session_length = properties::get_duration(
+                            &contents,
+                            "start-session-length",
+                            Duration::hours(2),
+                        );
+                        skip_failed_session(run_session(
+                            context,
+                            notifiers,
+                            Some(context.clock.now() + session_length),
+                            controls,
+                            status,
+                        ))?;
+                    }
+                }
+                None => {
+                    return Err(LauncherError::Config(
+                        "session-schedule has no upcoming windows!".to_owned(),
+                    ))
+                }
+            },
+        }
+    }
+    Ok(())
+}
+
+// Sessions that couldn't run or crashed leave the schedule going, other errors end it
+fn skip_failed_session(result: Result<(), LauncherError>) -> Result<(), LauncherError> {
+    match result {
+        Err(error @ LauncherError::Preflight)
+        | Err(error @ LauncherError::Locked(_))
+        | Err(error @ LauncherError::Crashed) => {
+            println!("[WARN] {}", error);
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+// Sleep until the given time, keeping the console responsive
+// Returns true if a start was requested in the meantime
+fn sleep_until(
+    context: &Context,
+    time: DateTime<Local>,
+    controls: &Receiver<Control>,
+    status: &SharedStatus,
+) -> bool {
+    status::update(status, |status| {
+        status.running = false;
+        status.next_session = Some(time);
+    });
+    while context.clock.now() < time && !control::terminating() {
+        while let Ok(control) = controls.try_recv() {
+            match control {
+                Control::Start => return true,
+                Control::Status => println!("[INFO] Server is offline until {}", time),
+                _ => println!("[WARN] Server is offline until {}", time),
+            }
+        }
+        context.clock.sleep(std::time::Duration::from_secs(1));
+    }
+    false
+}
+
+// Ask for the scheduled shutdown time
+fn read_scheduled_time(clock: &clock::Clock) -> DateTime<Local> {
+    let start_time = clock.now();
+    let mut scheduled_time;
+
+    // Get scheduled time
+    loop {
+        println!("\nInsert time for scheduled server shutdown");
+
+        // Read hours for scheduled shutdown
+        print!("Hours > ");
+        let _ = stdout().flush();
+        let mut scheduled_hours = String::new();
+        let scheduled_hours = match io::stdin().read_line(&mut scheduled_hours) {
+            Ok(_) => {
+                let scheduled_hours = match scheduled_hours.trim().parse::<u32>() {
+                    Ok(scheduled_hours) => {
+                        if scheduled_hours > 23 {
+                            println!("[WARN] Hours must be between 0 and 23");
+                            continue;
+                        };
+                        scheduled_hours
+                    }
+                    Err(error) => {
+                        println!("[WARN] Failed to parse number: {}", error);
+                        continue;
+                    }
+                };
+                scheduled_hours
+            }
+            Err(error) => {
+                println!("[WARN] Failed to read input: {}", error);
+                continue;
+            }
+        };
+
+        // Read minutes for scheduled shutdown
+        print!("Minutes > ");
+        let _ = stdout().flush();
+        let mut scheduled_minutes = String::new();
+        let scheduled_minutes = match io::stdin().read_line(&mut scheduled_minutes) {
+            Ok(_) => {
+                let scheduled_minutes = match scheduled_minutes.trim().parse::<u32>() {
+                    Ok(scheduled_minutes) => {
+                        if scheduled_minutes > 59 {
+                            println!("[WARN] Minutes must be between 0 and 59");
+                            continue;
+                        };
+                        scheduled_minutes
+                    }
+                    Err(error) => {
+                        println!("[WARN] Failed to parse number: {}", error);
+                        continue;
+                    }
+                };
+                scheduled_minutes
+            }
+            Err(error) => {
+                println!("[WARN] Failed to read input: {}", error);
+                continue;
+            }
+        };
+
+        // Convert into datetime, some times don't exist on days clocks go forward
+        let time = NaiveTime::from_hms(scheduled_hours, scheduled_minutes, 0);
+        scheduled_time = match Local
+            .from_local_datetime(&start_time.naive_local().date().and_time(time))
+            .earliest()
+        {
+            Some(scheduled_time) => scheduled_time,
+            None => {
+                println!("[WARN] That time doesn't exist today");
+                continue;
+            }
+        };
+        if scheduled_time <= start_time {
+            scheduled_time = scheduled_time + Duration::days(1);
+        }
+        break;
+    }
+    println!("[INFO] Shutdown scheduled for {}", scheduled_time);
+    scheduled_time
+}
+
+// Launch the server and supervise it until the scheduled shutdown
+pub fn run_session(
+    context: &Context,
+    notifiers: &Notifiers,
+    scheduled_time: Option<DateTime<Local>>,
+    controls: &Receiver<Control>,
+    status: &SharedStatus,
+) -> Result<(), LauncherError> {
+    let server_properties_path = context.path("server.properties");
+    let server_properties_temporary_path = context.path("server.properties.tmp");
+    let contents = match fs::read_to_string(&server_properties_path) {
+        Ok(contents) => contents,
+        Err(error) => {
+            return Err(LauncherError::Config(format!(
+                "Failed to open server.properties: {}",
+                error
+            )))
+        }
+    };
+
+    // Catch problems before anything is changed or announced
+    if !preflight::check(context, &contents) {
+        return Err(LauncherError::Preflight);
+    }
+
+    // Grab level name and server version, pre-flight made sure they're set
+    let level_name = properties::get(&contents, "level-name").unwrap_or_default();
+    let server_version = properties::get(&contents, "server-version").unwrap_or_default();
+
+    // Read level.dat, missing until the world is first generated
+    let server_version_jar_path = context.path(format!("jars/{}.jar", server_version));
+    let world = match WorldInfo::load(&context.path(level_name)) {
+        Ok(world) => Some(world),
+        Err(error) if error.kind() == ErrorKind::NotFound => None,
+        Err(error) => {
+            println!("[WARN] Failed to read {}/level.dat: {}", level_name, error);
+            None
+        }
+    };
+    if let Some(world) = &world {
+        if let Err(error) = world::check_compatibility(world, &server_version_jar_path) {
+            if !context.force {
+                return Err(LauncherError::Incompatible(format!(
+                    "{}, launch with --force to open the world anyway",
+                    error
+                )));
+            }
+            println!("[WARN] {}", error);
+        }
+    }
+
+    // Acquire server lock, released when the session is over or on any error
+    let lock = ServerLock::acquire(context)?;
+
+    // Replace motd text, the original is put back when the session is over
+    let motd_guard = MotdGuard::new(context, &contents);
+    let time = scheduled_time.map(|time| time.to_string());
+    let args = [
+        ("time", time.as_deref().unwrap_or_default()),
+        ("level", level_name),
+        ("version", server_version),
+    ];
+    let motd = match scheduled_time {
+        Some(_) => messages::format("motd.scheduled", &args),
+        None => messages::format("motd", &args),
+    };
+    let regex = Regex::new("motd=(.*)").unwrap();
+    let updated_contents = regex
+        .replace_all(&contents, NoExpand(&format!("motd={}", motd)))
+        .to_string();
+
+    // Write updated content to temporary server.properties file
+    match fs::write(&server_properties_temporary_path, &updated_contents) {
+        Ok(()) => match fs::rename(&server_properties_temporary_path, &server_properties_path) {
+            Ok(()) => println!("[INFO] Server motd updated"),
+            Err(error) => println!(
+                "[ERROR] Failed to replace server.properties with server.properties.tmp: {}",
+                error
+            ),
+        },
+        Err(error) => println!(
+            "[ERROR] Couldn't write to server.properties.tmp file: {}",
+            error
+        ),
+    }
+
+    // Put the server version jar in place
+    jar::install(context, server_version).map_err(LauncherError::Jar)?;
+
+    // Send launching notification
+    notifiers.notify(&Event::Launching {
+        level_name,
+        server_version,
+        server_host: &lock.whoami,
+        shutdown_time: scheduled_time,
+        world: world.as_ref(),
+    });
+
+    // Launch server process and supervise it
+    let mut session = match Session::launch(
+        context,
+        notifiers,
+        level_name,
+        server_version,
+        scheduled_time,
+        &updated_contents,
+        status.clone(),
+    ) {
+        Ok(session) => session,
+        Err(error) => {
+            jar::restore(context);
+            return Err(error);
+        }
+    };
+    let report = session.supervise(controls);
+    // A jar that never got the server online goes back to the previous one
+    if report.reason == ShutdownReason::Crash && !session.went_online() {
+        jar::restore(context);
+    }
+
+    // Release server lock
+    let whoami = lock.whoami.clone();
+    drop(motd_guard);
+    drop(lock);
+    history::record(context, &report, &whoami);
+    playtime::record(context, &report);
+    // Send shutdown notification
+    if report.reason == ShutdownReason::Crash {
+        notifiers.notify(&Event::Crash(&report));
+    } else {
+        notifiers.notify(&Event::Shutdown(&report));
+    }
+    if let Some(players) = playtime::weekly_leaderboard(context) {
+        notifiers.notify(&Event::Leaderboard { players: &players });
+    }
+    if report.reason == ShutdownReason::Crash {
+        return Err(LauncherError::Crashed);
+    }
+    Ok(())
+}
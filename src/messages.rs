@@ -0,0 +1,148 @@
+use crate::context::Context;
+use crate::properties;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+// Locale used when server.properties doesn't pick one, the launcher's original language
+const DEFAULT_LOCALE: &str = "pt-PT";
+
+// Catalogs built into the launcher
+const BUILTIN_LOCALES: [(&str, &str); 2] = [
+    ("en-US", include_str!("../locales/en-US.properties")),
+    ("pt-PT", include_str!("../locales/pt-PT.properties")),
+];
+
+// Catalog for this instance, picked once at startup
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+// Messages for one locale, falling back to the default locale
+struct Catalog {
+    messages: HashMap<String, String>,
+    fallback: HashMap<String, String>,
+}
+
+impl Catalog {
+    fn new(root: &Path, locale: &str) -> Catalog {
+        let mut messages = builtin(locale).map(parse).unwrap_or_default();
+
+        // Files next to the server override the built-in messages
+        let path = root.join(format!("locales/{}.properties", locale));
+        if path.exists() {
+            match fs::read_to_string(&path) {
+                Ok(contents) => messages.extend(parse(&contents)),
+                Err(error) => println!("[WARN] Failed to read {}: {}", path.display(), error),
+            }
+        } else if messages.is_empty() {
+            println!(
+                "[WARN] Unknown locale '{}', using {}",
+                locale, DEFAULT_LOCALE
+            );
+        }
+
+        Catalog {
+            messages,
+            fallback: parse(builtin(DEFAULT_LOCALE).unwrap()),
+        }
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        match self.messages.get(key).or_else(|| self.fallback.get(key)) {
+            Some(message) => message,
+            None => key,
+        }
+    }
+
+    fn format(&self, key: &str, args: &[(&str, &str)]) -> String {
+        let mut message = self.get(key).to_owned();
+        for (name, value) in args {
+            message = message.replace(&format!("{{{}}}", name), value);
+        }
+        message
+    }
+}
+
+// Pick the locale set by launcher-locale in server.properties
+pub fn load(context: &Context) {
+    let locale = fs::read_to_string(context.path("server.properties"))
+        .ok()
+        .and_then(|contents| properties::get(&contents, "launcher-locale").map(str::to_owned))
+        .unwrap_or_else(|| DEFAULT_LOCALE.to_owned());
+    println!("[INFO] Using {} messages", locale);
+    let _ = CATALOG.set(Catalog::new(&context.root, &locale));
+}
+
+// Look up a message
+pub fn get(key: &str) -> String {
+    format(key, &[])
+}
+
+// Look up a message, replacing placeholders such as {time}
+pub fn format(key: &str, args: &[(&str, &str)]) -> String {
+    CATALOG
+        .get_or_init(|| Catalog::new(Path::new("."), DEFAULT_LOCALE))
+        .format(key, args)
+}
+
+fn builtin(locale: &str) -> Option<&'static str> {
+    BUILTIN_LOCALES
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(locale))
+        .map(|(_, contents)| *contents)
+}
+
+// Read "key=value" lines, skipping blank lines and # comments
+fn parse(contents: &str) -> HashMap<String, String> {
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_owned(), value.to_owned()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    #[test]
+    fn falls_back_to_the_default_locale() {
+        let root = env::temp_dir().join(format!("launcher-messages-{}", process::id()));
+        fs::create_dir_all(root.join("locales")).unwrap();
+        fs::write(
+            root.join("locales/fr-FR.properties"),
+            "# Only some messages\nsay.postponed=Arrêt du serveur repoussé de {duration}.\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("locales/en-US.properties"),
+            "say.postponed=Shutdown pushed back by {duration}!\n",
+        )
+        .unwrap();
+        let postponed = |catalog: &Catalog| catalog.format("say.postponed", &[("duration", "5m")]);
+
+        let default = Catalog::new(&root, DEFAULT_LOCALE);
+        assert_eq!(postponed(&default), "Encerramento do servidor adiado 5m.");
+
+        // Missing messages come from the default locale
+        let french = Catalog::new(&root, "fr-FR");
+        assert_eq!(postponed(&french), "Arrêt du serveur repoussé de 5m.");
+        assert_eq!(french.get("event.crash"), default.get("event.crash"));
+
+        // Files next to the server override built-in messages
+        let english = Catalog::new(&root, "en-US");
+        assert_eq!(postponed(&english), "Shutdown pushed back by 5m!");
+        assert_eq!(
+            english.get("event.crash"),
+            parse(builtin("en-US").unwrap())["event.crash"]
+        );
+
+        let unknown = Catalog::new(&root, "xx-XX");
+        assert_eq!(unknown.get("event.crash"), default.get("event.crash"));
+        assert_eq!(unknown.get("no.such.key"), "no.such.key");
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,302 @@
+// Prometheus metrics, served at /metrics when metrics-port is set in server.properties
+use crate::clock::Clock;
+use crate::context::Context;
+use crate::properties;
+use crate::status::{self, SharedStatus, Status};
+use crate::webhook;
+use std::fmt::Write;
+use std::fs;
+use std::thread;
+use tiny_http::{Header, Response, Server};
+
+// Clock ticks per second in /proc/<pid>/stat, fixed at 100 on Linux
+const CLOCK_TICKS: f64 = 100.0;
+
+// Where to serve metrics, from server.properties
+pub struct MetricsConfig {
+    address: String,
+    port: u16,
+    clock: Clock,
+}
+
+impl MetricsConfig {
+    // Metrics are enabled by metrics-port, and only served locally unless metrics-address says otherwise
+    pub fn load(context: &Context) -> Option<MetricsConfig> {
+        let contents = fs::read_to_string(context.path("server.properties")).unwrap_or_default();
+        properties::get(&contents, "metrics-port")?;
+        Some(MetricsConfig {
+            address: properties::get(&contents, "metrics-address")
+                .unwrap_or("127.0.0.1")
+                .to_owned(),
+            port: properties::get_number(&contents, "metrics-port", 9225),
+            clock: context.clock,
+        })
+    }
+}
+
+// Serve metrics on a background thread
+pub fn spawn(config: MetricsConfig, status: SharedStatus) {
+    let address = format!("{}:{}", config.address, config.port);
+    let server = match Server::http(&address) {
+        Ok(server) => server,
+        Err(error) => {
+            println!("[WARN] Failed to serve metrics on {}: {}", address, error);
+            return;
+        }
+    };
+    println!("[INFO] Serving metrics on http://{}/metrics", address);
+    let clock = config.clock;
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let response = if request.url() == "/metrics" {
+                let content_type =
+                    Header::from_bytes("Content-Type", "text/plain; version=0.0.4").unwrap();
+                Response::from_string(render(&status::snapshot(&status), &clock))
+                    .with_header(content_type)
+            } else {
+                Response::from_string("Not Found").with_status_code(404)
+            };
+            if let Err(error) = request.respond(response) {
+                println!("[WARN] Failed to answer metrics request: {}", error);
+            }
+        }
+    });
+}
+
+// Text exposition format, one family per metric
+fn render(status: &Status, clock: &Clock) -> String {
+    let mut metrics = Metrics::default();
+    let now = clock.now();
+    let flag = |value: bool| if value { 1.0 } else { 0.0 };
+
+    metrics.gauge(
+        "minecraft_server_running",
+        "Whether the server process is running",
+        flag(status.running),
+    );
+    metrics.gauge(
+        "minecraft_server_up",
+        "Whether the server answers status pings",
+        flag(status.running && status.server_status.is_some()),
+    );
+    if let (true, Some(launch_time)) = (status.running, status.launch_time) {
+        metrics.gauge(
+            "minecraft_server_uptime_seconds",
+            "Time since the server process was launched",
+            (now - launch_time).num_seconds() as f64,
+        );
+    }
+    if let (true, Some(scheduled_time)) = (status.running, status.scheduled_time) {
+        metrics.gauge(
+            "minecraft_server_shutdown_seconds",
+            "Time left until the scheduled shutdown",
+            (scheduled_time - now).num_seconds().max(0) as f64,
+        );
+    }
+    if status.running {
+        metrics.info(
+            "minecraft_server_info",
+            "World and Minecraft version of the running server",
+            &[
+                ("level", &status.level_name),
+                ("version", &status.server_version),
+            ],
+        );
+    }
+    metrics.counter(
+        "minecraft_server_restarts_total",
+        "Restarts within sessions",
+        status.restarts as f64,
+    );
+
+    metrics.gauge(
+        "minecraft_players_online",
+        "Players currently online",
+        status.online_players.len() as f64,
+    );
+    if let Some(server_status) = &status.server_status {
+        metrics.gauge(
+            "minecraft_players_max",
+            "Player slots the server reports",
+            server_status.max_players as f64,
+        );
+        metrics.gauge(
+            "minecraft_ping_latency_seconds",
+            "Round trip of the latest status ping",
+            server_status.latency.as_secs_f64(),
+        );
+    }
+    if let Some(tps) = status.tps {
+        metrics.gauge(
+            "minecraft_tps",
+            "Ticks per second over the last minute",
+            tps,
+        );
+    }
+
+    if let Some((resident, cpu)) = status.pid.and_then(process_stats) {
+        metrics.gauge(
+            "minecraft_process_resident_memory_bytes",
+            "Resident memory of the server process",
+            resident as f64,
+        );
+        metrics.counter(
+            "minecraft_process_cpu_seconds_total",
+            "CPU time used by the server process",
+            cpu,
+        );
+    }
+
+    metrics.counter(
+        "minecraft_backups_total",
+        "Finished world backups",
+        status.backups as f64,
+    );
+    metrics.counter(
+        "minecraft_backup_failures_total",
+        "Failed world backups",
+        status.failed_backups as f64,
+    );
+    if let Some(duration) = status.last_backup_duration {
+        metrics.gauge(
+            "minecraft_backup_duration_seconds",
+            "Time the latest backup took",
+            duration.as_secs_f64(),
+        );
+    }
+    metrics.counter(
+        "minecraft_webhook_failures_total",
+        "Notifications that couldn't be delivered after retrying",
+        webhook::failures() as f64,
+    );
+    metrics.text
+}
+
+// Resident memory in bytes and CPU time in seconds, read from /proc
+fn process_stats(pid: u32) -> Option<(u64, f64)> {
+    let resident = fs::read_to_string(format!("/proc/{}/status", pid))
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()?
+        * 1024;
+
+    // Fields after the command name, which may contain spaces, utime and stime are 14th and 15th
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    let fields = stat[stat.rfind(')')? + 1..]
+        .split_whitespace()
+        .collect::<Vec<_>>();
+    let utime = fields.get(11)?.parse::<u64>().ok()?;
+    let stime = fields.get(12)?.parse::<u64>().ok()?;
+    Some((resident, (utime + stime) as f64 / CLOCK_TICKS))
+}
+
+#[derive(Default)]
+struct Metrics {
+    text: String,
+}
+
+impl Metrics {
+    fn gauge(&mut self, name: &str, help: &str, value: f64) {
+        self.metric(name, "gauge", help, value);
+    }
+
+    fn counter(&mut self, name: &str, help: &str, value: f64) {
+        self.metric(name, "counter", help, value);
+    }
+
+    // Gauge that's always 1, the labels carry the information
+    fn info(&mut self, name: &str, help: &str, labels: &[(&str, &str)]) {
+        let labels = labels
+            .iter()
+            .map(|(label, value)| format!("{}=\"{}\"", label, escape(value)))
+            .collect::<Vec<String>>()
+            .join(",");
+        self.metric(&format!("{}{{{}}}", name, labels), "gauge", help, 1.0);
+    }
+
+    fn metric(&mut self, sample: &str, kind: &str, help: &str, value: f64) {
+        // Labels only go on the sample
+        let name = sample.split('{').next().unwrap_or(sample);
+        let _ = writeln!(self.text, "# HELP {} {}", name, help);
+        let _ = writeln!(self.text, "# TYPE {} {}", name, kind);
+        let _ = writeln!(self.text, "{} {}", sample, value);
+    }
+}
+
+// Label values may hold anything, such as quotes in a level name
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::prelude::{Local, TimeZone};
+    use chrono::Duration;
+
+    #[test]
+    fn renders_names_types_and_labels() {
+        let now = Local.ymd(2021, 5, 1).and_hms(20, 0, 0);
+        let status = Status {
+            running: true,
+            level_name: "Steve's \"big\" world\\1\n".to_owned(),
+            server_version: "1.16.5".to_owned(),
+            launch_time: Some(now - Duration::minutes(90)),
+            // A second ahead, the clock keeps running while rendering
+            scheduled_time: Some(now + Duration::seconds(1801)),
+            online_players: vec!["Steve".to_owned(), "Alex".to_owned()],
+            tps: Some(19.5),
+            restarts: 2,
+            ..Status::default()
+        };
+        let text = render(&status, &Clock::simulated(now, 1));
+        let lines = text.lines().collect::<Vec<&str>>();
+
+        let sample = |name: &str| {
+            let index = lines
+                .iter()
+                .position(|line| *line == format!("# TYPE {}", name))
+                .unwrap_or_else(|| panic!("{} missing", name));
+            assert!(lines[index - 1]
+                .starts_with(&format!("# HELP {} ", name.split(' ').next().unwrap())));
+            lines[index + 1]
+        };
+        assert_eq!(
+            sample("minecraft_server_running gauge"),
+            "minecraft_server_running 1"
+        );
+        assert_eq!(sample("minecraft_server_up gauge"), "minecraft_server_up 0");
+        assert_eq!(
+            sample("minecraft_server_uptime_seconds gauge"),
+            "minecraft_server_uptime_seconds 5400"
+        );
+        assert_eq!(
+            sample("minecraft_server_shutdown_seconds gauge"),
+            "minecraft_server_shutdown_seconds 1800"
+        );
+        assert_eq!(
+            sample("minecraft_server_restarts_total counter"),
+            "minecraft_server_restarts_total 2"
+        );
+        assert_eq!(
+            sample("minecraft_players_online gauge"),
+            "minecraft_players_online 2"
+        );
+        assert_eq!(sample("minecraft_tps gauge"), "minecraft_tps 19.5");
+        assert_eq!(
+            sample("minecraft_server_info gauge"),
+            r#"minecraft_server_info{level="Steve's \"big\" world\\1\n",version="1.16.5"} 1"#
+        );
+        // Only reported once known
+        assert!(!text.contains("minecraft_players_max"));
+        assert!(!text.contains("minecraft_backup_duration_seconds"));
+    }
+}
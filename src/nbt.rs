@@ -0,0 +1,173 @@
+// Named Binary Tag, the format of level.dat and other world files
+use flate2::read::GzDecoder;
+use std::fs::File;
+use std::io::{self, BufReader, ErrorKind, Read};
+use std::path::Path;
+
+// Deepest nesting accepted, real files stay far below it
+const MAX_DEPTH: usize = 512;
+
+// Every tag type is read, including the ones the launcher never looks at
+#[allow(dead_code)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(Vec<Tag>),
+    Compound(Vec<(String, Tag)>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Tag {
+    // Child of a compound
+    pub fn get(&self, name: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(children) => children
+                .iter()
+                .find(|(child, _)| child == name)
+                .map(|(_, tag)| tag),
+            _ => None,
+        }
+    }
+
+    // Any integer type, widened
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Tag::Byte(value) => Some(*value as i64),
+            Tag::Short(value) => Some(*value as i64),
+            Tag::Int(value) => Some(*value as i64),
+            Tag::Long(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+// Read a gzip compressed file such as level.dat, returning its root compound
+pub fn read_gzip(path: &Path) -> io::Result<Tag> {
+    let file = File::open(path)?;
+    let (_, tag) = read(&mut GzDecoder::new(BufReader::new(file)))?;
+    Ok(tag)
+}
+
+// Read a named tag, the root of every NBT file
+pub fn read(reader: &mut impl Read) -> io::Result<(String, Tag)> {
+    let kind = read_u8(reader)?;
+    if kind == 0 {
+        return Err(invalid("empty root tag".to_owned()));
+    }
+    let name = read_string(reader)?;
+    let tag = read_payload(reader, kind, 0)?;
+    Ok((name, tag))
+}
+
+fn read_payload(reader: &mut impl Read, kind: u8, depth: usize) -> io::Result<Tag> {
+    if depth > MAX_DEPTH {
+        return Err(invalid("tags nested too deep".to_owned()));
+    }
+    Ok(match kind {
+        1 => Tag::Byte(read_u8(reader)? as i8),
+        2 => Tag::Short(i16::from_be_bytes(read_array(reader)?)),
+        3 => Tag::Int(i32::from_be_bytes(read_array(reader)?)),
+        4 => Tag::Long(i64::from_be_bytes(read_array(reader)?)),
+        5 => Tag::Float(f32::from_be_bytes(read_array(reader)?)),
+        6 => Tag::Double(f64::from_be_bytes(read_array(reader)?)),
+        7 => {
+            let length = read_length(reader)?;
+            let mut bytes = Vec::new();
+            reader
+                .by_ref()
+                .take(length as u64)
+                .read_to_end(&mut bytes)?;
+            if bytes.len() < length {
+                return Err(ErrorKind::UnexpectedEof.into());
+            }
+            Tag::ByteArray(bytes.into_iter().map(|byte| byte as i8).collect())
+        }
+        8 => Tag::String(read_string(reader)?),
+        9 => {
+            let item_kind = read_u8(reader)?;
+            let length = read_length(reader)?;
+            let mut items = Vec::new();
+            // Empty lists may use the end tag as their item type
+            if item_kind != 0 || length > 0 {
+                for _ in 0..length {
+                    items.push(read_payload(reader, item_kind, depth + 1)?);
+                }
+            }
+            Tag::List(items)
+        }
+        10 => {
+            let mut children = Vec::new();
+            loop {
+                let child_kind = read_u8(reader)?;
+                if child_kind == 0 {
+                    break;
+                }
+                let name = read_string(reader)?;
+                children.push((name, read_payload(reader, child_kind, depth + 1)?));
+            }
+            Tag::Compound(children)
+        }
+        11 => {
+            let length = read_length(reader)?;
+            let mut values = Vec::with_capacity(length.min(1 << 16));
+            for _ in 0..length {
+                values.push(i32::from_be_bytes(read_array(reader)?));
+            }
+            Tag::IntArray(values)
+        }
+        12 => {
+            let length = read_length(reader)?;
+            let mut values = Vec::with_capacity(length.min(1 << 16));
+            for _ in 0..length {
+                values.push(i64::from_be_bytes(read_array(reader)?));
+            }
+            Tag::LongArray(values)
+        }
+        _ => return Err(invalid(format!("unknown tag type {}", kind))),
+    })
+}
+
+fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
+    let [byte] = read_array(reader)?;
+    Ok(byte)
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_length(reader: &mut impl Read) -> io::Result<usize> {
+    let length = i32::from_be_bytes(read_array(reader)?);
+    if length < 0 {
+        return Err(invalid(format!("negative length {}", length)));
+    }
+    Ok(length as usize)
+}
+
+// Strings are modified UTF-8, which only differs for NUL and characters outside the BMP
+fn read_string(reader: &mut impl Read) -> io::Result<String> {
+    let length = u16::from_be_bytes(read_array(reader)?) as usize;
+    let mut bytes = vec![0; length];
+    reader.read_exact(&mut bytes)?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,872 @@
+use super::{scheduled_time, Event, Notifier};
+use crate::clock::Clock;
+use crate::context::Context;
+use crate::control;
+use crate::messages;
+use crate::properties;
+use crate::report::SessionReport;
+use crate::secrets;
+use crate::status::Status;
+use crate::webhook;
+use crate::world::WorldInfo;
+use chrono::prelude::{DateTime, Local};
+use chrono::Duration;
+use serde_json::{json, Map, Value};
+use sha1::{Digest, Sha1};
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+// IDs of the status messages being edited, by SHA-1 of the webhook URL so the
+// tokens in them aren't written to disk
+const LIVE_MESSAGES_PATH: &str = "discord.messages";
+
+// Embed colours for the session states
+const LAUNCHING_COLOR: u32 = 16492544;
+const ONLINE_COLOR: u32 = 3451439;
+const COUNTDOWN_COLOR: u32 = 15105570;
+
+// Default webhook avatar and embed footer icon
+const DEFAULT_AVATAR_URL: &str = "https://i.imgur.com/KeSlNUv.png";
+const DEFAULT_FOOTER_ICON_URL: &str = "https://i.imgur.com/DHgRvnF.png";
+
+// Server icon shown in the launching and shutdown embeds
+const SERVER_ICON_FILENAME: &str = "server-icon.png";
+
+// Discord webhook, queueing messages it can't deliver
+pub struct DiscordNotifier {
+    identity: Identity,
+    url: String,
+    // Upload server-icon.png with the launching and shutdown messages
+    attach_icon: bool,
+    // Edit one status message per session instead of posting every change
+    edit_messages: bool,
+    // Least time between edits for player count and time remaining
+    edit_interval: Duration,
+    live: RefCell<Option<LiveMessage>>,
+    // ID of the status message, set by the delivery thread once it's posted
+    live_id: Arc<Mutex<Option<String>>>,
+    // Where undeliverable messages and status message IDs are kept
+    queue_path: PathBuf,
+    live_messages_path: PathBuf,
+    server_icon_path: PathBuf,
+    clock: Clock,
+}
+
+// How the webhook presents itself
+struct Identity {
+    username: String,
+    avatar_url: String,
+    footer_icon_url: String,
+    thumbnail_url: Option<String>,
+}
+
+// Status message of the current session
+struct LiveMessage {
+    title: String,
+    color: u32,
+    level_name: String,
+    server_version: String,
+    server_host: String,
+    shutdown_time: Option<DateTime<Local>>,
+    // Details from level.dat
+    world: Vec<(String, String)>,
+    players: Vec<String>,
+    // Embeds show the uploaded server icon
+    icon: bool,
+    last_content: Value,
+    last_edit: Instant,
+}
+
+impl DiscordNotifier {
+    // Webhook settings come from server.properties, messages go to a forum thread when given
+    pub fn new(
+        context: &Context,
+        app_name: &str,
+        url: &str,
+        thread_id: Option<&str>,
+    ) -> DiscordNotifier {
+        let contents = fs::read_to_string(context.path("server.properties")).unwrap_or_default();
+        let setting = |key, default: &str| {
+            properties::get(&contents, key)
+                .filter(|value| !value.is_empty())
+                .unwrap_or(default)
+                .to_owned()
+        };
+        DiscordNotifier {
+            identity: Identity {
+                username: setting("discord-username", app_name),
+                avatar_url: setting("discord-avatar-url", DEFAULT_AVATAR_URL),
+                footer_icon_url: setting("discord-footer-icon-url", DEFAULT_FOOTER_ICON_URL),
+                thumbnail_url: properties::get(&contents, "discord-thumbnail-url")
+                    .filter(|url| !url.is_empty())
+                    .map(str::to_owned),
+            },
+            url: match thread_id {
+                Some(thread_id) => with_thread(url, thread_id),
+                None => url.to_owned(),
+            },
+            attach_icon: properties::get(&contents, "discord-attach-icon") != Some("false"),
+            edit_messages: properties::get(&contents, "discord-edit-messages") != Some("false"),
+            edit_interval: properties::get_duration(
+                &contents,
+                "discord-edit-interval",
+                Duration::minutes(1),
+            ),
+            live: RefCell::new(None),
+            live_id: Arc::new(Mutex::new(None)),
+            queue_path: context.path(webhook::QUEUE_PATH),
+            live_messages_path: context.path(LIVE_MESSAGES_PATH),
+            server_icon_path: context.path(SERVER_ICON_FILENAME),
+            clock: context.clock,
+        }
+    }
+
+    pub fn edits_messages(&self) -> bool {
+        self.edit_messages
+    }
+
+    // server-icon.png, if there's one to upload
+    fn server_icon(&self) -> Option<webhook::Attachment> {
+        if !self.attach_icon {
+            return None;
+        }
+        fs::read(&self.server_icon_path)
+            .ok()
+            .map(|bytes| webhook::Attachment {
+                filename: SERVER_ICON_FILENAME.to_owned(),
+                bytes,
+            })
+    }
+
+    // Add the configured thumbnail, and the server icon when it's uploaded
+    fn decorate(&self, content: &mut Value, icon: bool) {
+        if let Some(embeds) = content["embeds"].as_array_mut() {
+            for embed in embeds {
+                if let Some(thumbnail_url) = &self.identity.thumbnail_url {
+                    embed["thumbnail"] = json!({ "url": thumbnail_url });
+                }
+                if icon {
+                    embed["image"] =
+                        json!({ "url": format!("attachment://{}", SERVER_ICON_FILENAME) });
+                }
+            }
+        }
+    }
+
+    // Post a message, with the server icon when asked for and available
+    fn post(&self, description: &'static str, mut content: Value, with_icon: bool) {
+        let icon = if with_icon { self.server_icon() } else { None };
+        self.decorate(&mut content, icon.is_some());
+        let (queue, url) = (self.queue_path.clone(), self.url.clone());
+        webhook::in_background(move || {
+            webhook::post(&queue, &url, description, &content, icon.as_ref())
+        });
+    }
+
+    // Post the session status message, or take over the one a previous run left behind
+    fn start_live(&self, mut live: LiveMessage) {
+        let icon = self.server_icon();
+        live.icon = icon.is_some();
+        let content = self.live_content(&live);
+        *self.live.borrow_mut() = Some(LiveMessage {
+            last_content: content.clone(),
+            last_edit: Instant::now(),
+            ..live
+        });
+
+        let (url, live_id) = (self.url.clone(), Arc::clone(&self.live_id));
+        let (queue, live_messages) = (self.queue_path.clone(), self.live_messages_path.clone());
+        webhook::in_background(move || {
+            let previous = load_live_id(&live_messages, &url);
+            let id = match previous.map(|id| (webhook::edit(&url, &id, &edit_body(&content)), id)) {
+                Some((Ok(()), id)) => {
+                    println!("[INFO] Resumed editing Discord message {}", id);
+                    Some(id)
+                }
+                _ => match webhook::create(&url, &content, icon.as_ref()) {
+                    Ok(id) => {
+                        println!("[INFO] Sent launching message to Discord webhook");
+                        Some(id)
+                    }
+                    Err(error) => {
+                        println!(
+                            "[WARN] Failed to create Discord status message, posting instead: {}",
+                            error
+                        );
+                        None
+                    }
+                },
+            };
+            if let Some(id) = &id {
+                save_live_id(&live_messages, &url, Some(id));
+            } else {
+                webhook::post(&queue, &url, "launching", &content, icon.as_ref());
+            }
+            *live_id.lock().unwrap() = id;
+        });
+    }
+
+    // Apply a change to the status message and push it right away
+    // `fallback`, if any, is posted instead when there's no status message to edit
+    fn edit_live<F: FnOnce(&mut LiveMessage)>(
+        &self,
+        change: F,
+        fallback: Option<(&'static str, Value)>,
+    ) -> bool {
+        let mut live = self.live.borrow_mut();
+        let live = match live.as_mut() {
+            Some(live) => live,
+            None => return false,
+        };
+        change(live);
+        let content = self.live_content(live);
+        self.send_edit(edit_body(&content), fallback);
+        live.last_content = content;
+        live.last_edit = Instant::now();
+        true
+    }
+
+    // Edit the status message once the delivery thread has posted it
+    fn send_edit(&self, content: Value, fallback: Option<(&'static str, Value)>) {
+        let (url, live_id) = (self.url.clone(), Arc::clone(&self.live_id));
+        let queue = self.queue_path.clone();
+        webhook::in_background(move || {
+            let id = live_id.lock().unwrap().clone();
+            match (id, fallback) {
+                (Some(id), _) => {
+                    if let Err(error) = webhook::edit(&url, &id, &content) {
+                        println!("[WARN] Failed to edit Discord status message: {}", error);
+                    }
+                }
+                (None, Some((description, fallback))) => {
+                    webhook::post(&queue, &url, description, &fallback, None)
+                }
+                (None, None) => (),
+            }
+        });
+    }
+
+    // Replace the status message with the session report
+    fn finish_live(&self, content: Value) -> bool {
+        let live = match self.live.borrow_mut().take() {
+            Some(live) => live,
+            None => return false,
+        };
+        let mut edited = edit_body(&content);
+        self.decorate(&mut edited, live.icon);
+        let icon = self.server_icon();
+        let mut posted = content;
+        self.decorate(&mut posted, icon.is_some());
+
+        let (url, live_id) = (self.url.clone(), Arc::clone(&self.live_id));
+        let (queue, live_messages) = (self.queue_path.clone(), self.live_messages_path.clone());
+        webhook::in_background(move || {
+            let id = live_id.lock().unwrap().take();
+            if let Some(id) = id {
+                save_live_id(&live_messages, &url, None);
+                match webhook::edit(&url, &id, &edited) {
+                    Ok(()) => {
+                        println!("[INFO] Sent shutdown message to Discord webhook");
+                        return;
+                    }
+                    Err(error) => println!(
+                        "[WARN] Failed to edit Discord status message, posting instead: {}",
+                        error
+                    ),
+                }
+            }
+            webhook::post(&queue, &url, "shutdown", &posted, icon.as_ref());
+        });
+        true
+    }
+
+    fn live_content(&self, live: &LiveMessage) -> Value {
+        let mut content = launching_message(
+            &self.identity,
+            &live.title,
+            &live.level_name,
+            &live.server_version,
+            &live.server_host,
+            &scheduled_time(live.shutdown_time),
+            &live.world,
+        );
+        let embed = &mut content["embeds"][0];
+        embed["color"] = json!(live.color);
+        if let Some(fields) = embed["fields"].as_array_mut() {
+            fields.push(json!({
+                "name": messages::get("field.online-players"),
+                "value": if live.players.is_empty() {
+                    "`0`".to_owned()
+                } else {
+                    format!(
+                        "`{}`: {}",
+                        live.players.len(),
+                        live.players
+                            .iter()
+                            .map(|player| format!("`{}`", player))
+                            .collect::<Vec<String>>()
+                            .join(", ")
+                    )
+                },
+                "inline": true
+            }));
+            if let Some(shutdown_time) = live.shutdown_time {
+                fields.push(json!({
+                    "name": messages::get("field.time-remaining"),
+                    "value": format!("`{}`", control::format_duration(shutdown_time - self.clock.now())),
+                    "inline": true
+                }));
+            }
+        }
+        self.decorate(&mut content, live.icon);
+        content
+    }
+}
+
+impl Notifier for DiscordNotifier {
+    fn name(&self) -> &'static str {
+        "Discord"
+    }
+
+    fn notify(&self, event: &Event) {
+        if self.edit_messages {
+            // Posted when the status message couldn't be created
+            let fallback = (event.kind().name(), text_message(&self.identity, event));
+            let edited = match event {
+                Event::Launching {
+                    level_name,
+                    server_version,
+                    server_host,
+                    shutdown_time,
+                    world,
+                } => {
+                    self.start_live(LiveMessage {
+                        title: event.title(),
+                        color: LAUNCHING_COLOR,
+                        level_name: level_name.to_string(),
+                        server_version: server_version.to_string(),
+                        server_host: server_host.to_string(),
+                        shutdown_time: *shutdown_time,
+                        world: world.map(WorldInfo::fields).unwrap_or_default(),
+                        players: Vec::new(),
+                        icon: false,
+                        last_content: Value::Null,
+                        last_edit: Instant::now(),
+                    });
+                    true
+                }
+                // Only ever shown on the status message, never posted
+                Event::Online { .. } => {
+                    self.edit_live(
+                        |live| {
+                            live.title = event.title();
+                            live.color = ONLINE_COLOR;
+                        },
+                        None,
+                    );
+                    true
+                }
+                Event::Reminder { .. } => self.edit_live(
+                    |live| {
+                        live.title = event.title();
+                        live.color = COUNTDOWN_COLOR;
+                    },
+                    Some(fallback),
+                ),
+                Event::Restarting { .. } => self.edit_live(
+                    |live| {
+                        live.title = event.title();
+                        live.color = LAUNCHING_COLOR;
+                        live.players.clear();
+                    },
+                    Some(fallback),
+                ),
+                Event::Shutdown(report) | Event::Crash(report) => {
+                    self.finish_live(shutdown_message(&self.identity, &event.title(), report))
+                }
+                _ => false,
+            };
+            if edited {
+                return;
+            }
+        }
+
+        let identity = &self.identity;
+        match event {
+            Event::Launching {
+                level_name,
+                server_version,
+                server_host,
+                shutdown_time,
+                world,
+            } => self.post(
+                "launching",
+                launching_message(
+                    identity,
+                    &event.title(),
+                    level_name,
+                    server_version,
+                    server_host,
+                    &scheduled_time(*shutdown_time),
+                    &world.map(WorldInfo::fields).unwrap_or_default(),
+                ),
+                true,
+            ),
+            Event::Shutdown(report) | Event::Crash(report) => self.post(
+                event.kind().name(),
+                shutdown_message(identity, &event.title(), report),
+                true,
+            ),
+            Event::NextSession {
+                start_time,
+                shutdown_time,
+            } => self.post(
+                "next session",
+                next_session_message(identity, &event.title(), *start_time, *shutdown_time),
+                false,
+            ),
+            // Nothing to edit when posting, and not worth a message of its own
+            Event::Online { .. } => (),
+            _ => self.post(event.kind().name(), text_message(identity, event), false),
+        }
+    }
+
+    // Keep player count and time remaining current, at most once per edit interval
+    fn update(&self, status: &Status) {
+        let mut live = self.live.borrow_mut();
+        let live = match live.as_mut() {
+            Some(live) => live,
+            None => return,
+        };
+        live.players = status.online_players.clone();
+        live.shutdown_time = status.scheduled_time;
+        if Duration::from_std(live.last_edit.elapsed()).unwrap_or_else(|_| Duration::zero())
+            < self.edit_interval
+        {
+            return;
+        }
+        let content = self.live_content(live);
+        if content == live.last_content {
+            return;
+        }
+        self.send_edit(edit_body(&content), None);
+        live.last_content = content;
+        live.last_edit = Instant::now();
+    }
+}
+
+// Send webhook messages to a thread, such as a forum post
+fn with_thread(url: &str, thread_id: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(mut url) => {
+            url.query_pairs_mut().append_pair("thread_id", thread_id);
+            url.to_string()
+        }
+        Err(_) => url.to_owned(),
+    }
+}
+
+// Webhook URLs look like https://discord.com/api/webhooks/<id>/<token>
+pub fn check_webhook_url(url: &str) -> Result<(), String> {
+    parse_webhook_url(url).map(|_| ())
+}
+
+// Parsed webhook URL, with the token as its last path segment
+fn parse_webhook_url(url: &str) -> Result<reqwest::Url, String> {
+    let url = reqwest::Url::parse(url).map_err(|error| error.to_string())?;
+    if url.scheme() != "https" {
+        return Err(format!("expected https, not {}", url.scheme()));
+    }
+    match url.host_str() {
+        Some("discord.com")
+        | Some("discordapp.com")
+        | Some("ptb.discord.com")
+        | Some("canary.discord.com") => (),
+        Some(host) => return Err(format!("{} is not a Discord host", host)),
+        None => return Err("no host".to_owned()),
+    }
+    // Empty segments, such as from a trailing slash, aren't allowed
+    let segments = url
+        .path_segments()
+        .map(|segments| segments.collect())
+        .unwrap_or_else(Vec::new);
+    // The API version is optional, as in /api/v10/webhooks/<id>/<token>
+    let rest = match segments.as_slice() {
+        ["api", "webhooks", rest @ ..] => rest,
+        ["api", version, "webhooks", rest @ ..] if version.starts_with('v') => rest,
+        _ => return Err("path is not /api/webhooks/<id>/<token>".to_owned()),
+    };
+    match rest {
+        [id, token]
+            if !id.is_empty()
+                && id.chars().all(|c| c.is_ascii_digit())
+                && !token.is_empty()
+                && token
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') => {}
+        _ => return Err("expected a numeric webhook ID followed by its token".to_owned()),
+    }
+    Ok(url)
+}
+
+// Webhook URL with the token hidden, safe to print
+// Only the host is kept of URLs that don't parse, the token could be anywhere in them
+pub fn redact_webhook_url(url: &str) -> String {
+    match parse_webhook_url(url) {
+        Ok(mut url) => {
+            if let Ok(mut segments) = url.path_segments_mut() {
+                segments.pop().push("***");
+            }
+            url.set_query(None);
+            url.to_string()
+        }
+        Err(_) => match reqwest::Url::parse(url) {
+            Ok(url) => format!(
+                "{}://{}/***",
+                url.scheme(),
+                url.host_str().unwrap_or_default()
+            ),
+            Err(_) => "***".to_owned(),
+        },
+    }
+}
+
+// Webhook identity can't be changed when editing
+fn edit_body(content: &Value) -> Value {
+    let mut body = content.clone();
+    if let Some(body) = body.as_object_mut() {
+        body.remove("username");
+        body.remove("avatar_url");
+    }
+    body
+}
+
+// Status message ID a previous run left for this webhook
+fn load_live_id(path: &Path, url: &str) -> Option<String> {
+    let contents = fs::read_to_string(path).ok()?;
+    let ids = serde_json::from_str::<Value>(&contents).ok()?;
+    ids[live_key(url)].as_str().map(str::to_owned)
+}
+
+// Remember or forget the status message ID for this webhook
+fn save_live_id(path: &Path, url: &str, id: Option<&str>) {
+    let mut ids = fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| serde_json::from_str::<Map<String, Value>>(&contents).ok())
+        .unwrap_or_default();
+    // Older launchers keyed the IDs by the URL itself, token included
+    let stale = ids
+        .keys()
+        .filter(|key| key.contains("://"))
+        .cloned()
+        .collect::<Vec<String>>();
+    for key in stale {
+        ids.remove(&key);
+    }
+    match id {
+        Some(id) => ids.insert(live_key(url), json!(id)),
+        None => ids.remove(&live_key(url)),
+    };
+    let result = if ids.is_empty() {
+        fs::remove_file(path).or(Ok(()))
+    } else {
+        secrets::write_private(path, &Value::Object(ids).to_string())
+    };
+    if let Err(error) = result {
+        println!("[ERROR] Failed to update {}: {}", path.display(), error);
+    }
+}
+
+fn live_key(url: &str) -> String {
+    Sha1::digest(url.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// Launching message
+fn launching_message(
+    identity: &Identity,
+    title: &str,
+    level_name: &str,
+    minecraft_version: &str,
+    server_host: &str,
+    shutdown_time: &str,
+    world: &[(String, String)],
+) -> Value {
+    let mut message = json!({
+      "content": title,
+      "username": identity.username,
+      "avatar_url": identity.avatar_url,
+      "embeds": [{
+      "color": 3451439,
+      "footer": {
+          "icon_url": identity.footer_icon_url,
+          "text": messages::get("footer.server-info")
+      },
+      "fields": [
+          {
+          "name": messages::get("field.level-name"),
+          "value": format!("`{}`", level_name),
+          "inline": true
+          },
+          {
+          "name": messages::get("field.version"),
+          "value": format!("`{}`", minecraft_version),
+          "inline": true
+          },
+          {
+          "name": messages::get("field.host"),
+          "value": format!("`{}`", server_host),
+          "inline": true
+          },
+          {
+          "name": messages::get("field.shutdown-time"),
+          "value": format!("`{}`", shutdown_time)
+          }
+      ]
+      }]
+    });
+    if let Some(fields) = message["embeds"][0]["fields"].as_array_mut() {
+        for (name, value) in world {
+            fields.push(json!({
+                "name": name,
+                "value": format!("`{}`", value),
+                "inline": true
+            }));
+        }
+    }
+    message
+}
+
+// Shutdown message
+fn shutdown_message(identity: &Identity, title: &str, report: &SessionReport) -> Value {
+    let players = if report.joined_players.is_empty() {
+        messages::get("field.nobody")
+    } else {
+        report
+            .playtimes()
+            .iter()
+            .map(|(player, time)| format!("`{}` {}", player, control::format_duration(*time)))
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+
+    json!({
+      "content": title,
+      "username": identity.username,
+      "avatar_url": identity.avatar_url,
+      "embeds": [{
+      "color": report.reason.color(),
+      "footer": {
+          "icon_url": identity.footer_icon_url,
+          "text": messages::get("footer.session-report")
+      },
+      "fields": [
+          {
+          "name": messages::get("field.reason"),
+          "value": format!("`{}`", report.reason.label()),
+          "inline": true
+          },
+          {
+          "name": messages::get("field.exit-status"),
+          "value": format!("`{}`", report.exit_status()),
+          "inline": true
+          },
+          {
+          "name": messages::get("field.duration"),
+          "value": format!("`{}`", control::format_duration(report.end_time - report.start_time)),
+          "inline": true
+          },
+          {
+          "name": messages::get("field.started"),
+          "value": format!("`{}`", report.start_time),
+          "inline": true
+          },
+          {
+          "name": messages::get("field.ended"),
+          "value": format!("`{}`", report.end_time),
+          "inline": true
+          },
+          {
+          "name": messages::get("field.world-size"),
+          "value": format!("`{}`", report.world_size()),
+          "inline": true
+          },
+          {
+          "name": messages::get("field.peak-players"),
+          "value": format!("`{}`", report.peak_players),
+          "inline": true
+          },
+          {
+          "name": messages::get("field.unique-players"),
+          "value": format!("`{}`", report.joined_players.len()),
+          "inline": true
+          },
+          {
+          "name": messages::get("field.players"),
+          "value": players
+          }
+      ]
+      }]
+    })
+}
+
+// Next session message
+fn next_session_message(
+    identity: &Identity,
+    title: &str,
+    start_time: DateTime<Local>,
+    shutdown_time: DateTime<Local>,
+) -> Value {
+    json!({
+      "content": title,
+      "username": identity.username,
+      "avatar_url": identity.avatar_url,
+      "embeds": [{
+      "color": 3451439,
+      "footer": {
+          "icon_url": identity.footer_icon_url,
+          "text": messages::get("footer.next-session")
+      },
+      "fields": [
+          {
+          "name": messages::get("field.starts"),
+          "value": format!("`{}`", start_time),
+          "inline": true
+          },
+          {
+          "name": messages::get("field.shutdown-time"),
+          "value": format!("`{}`", shutdown_time),
+          "inline": true
+          }
+      ]
+      }]
+    })
+}
+
+// Any other event as plain text
+fn text_message(identity: &Identity, event: &Event) -> Value {
+    json!({
+      "content": event.text(),
+      "username": identity.username,
+      "avatar_url": identity.avatar_url
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub::Stub;
+    use std::env;
+    use std::process;
+
+    // Notifier posting to the stub from its own server directory
+    fn notifier(name: &str, stub: &Stub, properties: &str) -> DiscordNotifier {
+        let root = env::temp_dir().join(format!("launcher-discord-{}-{}", process::id(), name));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("server.properties"), properties).unwrap();
+        let context = Context {
+            root,
+            ..Context::default()
+        };
+        DiscordNotifier::new(&context, "Test", &format!("{}/hook", stub.url), None)
+    }
+
+    fn online() -> Event<'static> {
+        Event::Online {
+            level_name: "world",
+            server_version: "1.16.5",
+            motd: "Hello",
+            max_players: 20,
+        }
+    }
+
+    #[test]
+    fn edits_online_and_posts_backups() {
+        let stub = Stub::start(vec![(200, Vec::new(), r#"{"id": "42"}"#.to_owned())]);
+        let discord = notifier("edit", &stub, "");
+        let saved = Ok(PathBuf::from("backups/world.zip"));
+        discord.notify(&Event::Launching {
+            level_name: "world",
+            server_version: "1.16.5",
+            server_host: "steve@laptop",
+            shutdown_time: None,
+            world: None,
+        });
+        discord.notify(&online());
+        discord.notify(&Event::Backup {
+            level_name: "world",
+            result: &saved,
+        });
+
+        let created = stub.next_request();
+        assert_eq!(
+            (created.method.as_str(), created.path.as_str()),
+            ("POST", "/hook?wait=true")
+        );
+        let edited = stub.next_request();
+        assert_eq!(
+            (edited.method.as_str(), edited.path.as_str()),
+            ("PATCH", "/hook/messages/42")
+        );
+        let content = serde_json::from_str::<Value>(&edited.body).unwrap();
+        assert_eq!(content["embeds"][0]["color"], ONLINE_COLOR);
+        let posted = stub.next_request();
+        assert_eq!(
+            (posted.method.as_str(), posted.path.as_str()),
+            ("POST", "/hook")
+        );
+        stub.assert_no_more_requests();
+        let _ = fs::remove_dir_all(discord.live_messages_path.parent().unwrap());
+    }
+
+    #[test]
+    fn never_posts_online() {
+        // Without a status message, whether or not it would be edited
+        for (name, properties) in &[
+            ("unstarted", ""),
+            ("posting", "discord-edit-messages=false\n"),
+        ] {
+            let stub = Stub::start(Vec::new());
+            let discord = notifier(name, &stub, properties);
+            let saved = Err("disk full".to_owned());
+            discord.notify(&online());
+            discord.notify(&Event::Backup {
+                level_name: "world",
+                result: &saved,
+            });
+            let posted = stub.next_request();
+            assert_eq!(
+                (posted.method.as_str(), posted.path.as_str()),
+                ("POST", "/hook")
+            );
+            stub.assert_no_more_requests();
+            let _ = fs::remove_dir_all(discord.live_messages_path.parent().unwrap());
+        }
+    }
+
+    #[test]
+    fn checks_webhook_urls() {
+        assert!(check_webhook_url("https://discord.com/api/webhooks/123/abc-DEF_9").is_ok());
+        assert!(check_webhook_url("https://discord.com/api/v10/webhooks/123/abc").is_ok());
+        assert!(check_webhook_url("http://discord.com/api/webhooks/123/abc").is_err());
+        assert!(check_webhook_url("https://example.com/api/webhooks/123/abc").is_err());
+        assert!(check_webhook_url("https://discord.com/api/webhooks/abc/123").is_err());
+        assert!(check_webhook_url("https://discord.com/api/webhooks/123").is_err());
+        assert!(check_webhook_url("https://discord.com/api/webhooks/123/secret/").is_err());
+        assert!(check_webhook_url("https://discord.com/api/webhooks/123//secret").is_err());
+        assert_eq!(
+            redact_webhook_url("https://discord.com/api/webhooks/123/secret"),
+            "https://discord.com/api/webhooks/123/***"
+        );
+        assert_eq!(
+            redact_webhook_url("https://discord.com/api/webhooks/123/secret/"),
+            "https://discord.com/***"
+        );
+    }
+}
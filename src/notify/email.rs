@@ -0,0 +1,150 @@
+use super::{string, Event, Notifier};
+use crate::webhook;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use serde_json::Value;
+
+// Email sent through an SMTP server
+pub struct EmailNotifier {
+    transport: SmtpTransport,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl EmailNotifier {
+    pub fn from_config(config: &Value) -> Result<EmailNotifier, String> {
+        let server = string(config, "server")?;
+
+        // STARTTLS on 587 unless told otherwise
+        let builder = match config["security"].as_str().unwrap_or("starttls") {
+            "starttls" => SmtpTransport::starttls_relay(server),
+            "tls" => SmtpTransport::relay(server),
+            "none" => Ok(SmtpTransport::builder_dangerous(server)),
+            other => return Err(format!("unknown security '{}'", other)),
+        }
+        .map_err(|error| format!("invalid server '{}': {}", server, error))?;
+        let builder = match config["port"].as_u64() {
+            Some(port) => builder.port(port as u16),
+            None => builder,
+        };
+        let builder = match (config["username"].as_str(), config["password"].as_str()) {
+            (Some(username), Some(password)) => {
+                builder.credentials(Credentials::new(username.to_owned(), password.to_owned()))
+            }
+            _ => builder,
+        };
+
+        let from = mailbox(string(config, "from")?)?;
+        let to = match &config["to"] {
+            Value::String(address) => vec![mailbox(address)?],
+            Value::Array(addresses) => addresses
+                .iter()
+                .map(|address| mailbox(address.as_str().unwrap_or_default()))
+                .collect::<Result<Vec<Mailbox>, String>>()?,
+            _ => return Err("missing to".to_owned()),
+        };
+        if to.is_empty() {
+            return Err("missing to".to_owned());
+        }
+
+        Ok(EmailNotifier {
+            transport: builder.build(),
+            from,
+            to,
+        })
+    }
+
+    // The email for an event, from and to the configured addresses
+    fn message(&self, event: &Event) -> Result<Message, String> {
+        let mut builder = Message::builder()
+            .from(self.from.clone())
+            .subject(event.title());
+        for to in &self.to {
+            builder = builder.to(to.clone());
+        }
+        builder
+            .body(event.text())
+            .map_err(|error| error.to_string())
+    }
+}
+
+fn mailbox(address: &str) -> Result<Mailbox, String> {
+    address
+        .parse()
+        .map_err(|error| format!("invalid address '{}': {}", address, error))
+}
+
+impl Notifier for EmailNotifier {
+    fn name(&self) -> &'static str {
+        "email"
+    }
+
+    fn notify(&self, event: &Event) {
+        let message = match self.message(event) {
+            Ok(message) => message,
+            Err(error) => {
+                println!(
+                    "[ERROR] Failed to build {} email: {}",
+                    event.kind().name(),
+                    error
+                );
+                return;
+            }
+        };
+        let (kind, transport) = (event.kind(), self.transport.clone());
+        webhook::in_background(move || match transport.send(&message) {
+            Ok(_) => println!("[INFO] Sent {} notification to email", kind.name()),
+            Err(error) => println!(
+                "[ERROR] Failed to send {} notification to email: {}",
+                kind.name(),
+                error
+            ),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn builds_an_email_for_each_recipient() {
+        let notifier = EmailNotifier::from_config(&json!({
+            "server": "smtp.example.com",
+            "from": "Launcher <launcher@example.com>",
+            "to": ["steve@example.com", "alex@example.com"],
+        }))
+        .unwrap();
+        let event = Event::Restarting { reason: "lag" };
+        let message = notifier.message(&event).unwrap();
+
+        assert_eq!(
+            message.headers().get_raw("Subject"),
+            Some(event.title().as_str())
+        );
+        let envelope = message.envelope();
+        assert_eq!(
+            envelope.from().map(ToString::to_string).as_deref(),
+            Some("launcher@example.com")
+        );
+        assert_eq!(
+            envelope
+                .to()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<String>>(),
+            ["steve@example.com", "alex@example.com"]
+        );
+
+        // Both are required
+        assert!(EmailNotifier::from_config(&json!({"server": "smtp.example.com"})).is_err());
+        assert!(EmailNotifier::from_config(&json!({
+            "server": "smtp.example.com",
+            "from": "launcher@example.com",
+            "to": [],
+        }))
+        .is_err());
+    }
+}
@@ -0,0 +1,38 @@
+use super::{log_delivery, string, Event, Notifier};
+use crate::webhook::{self, RetryPolicy};
+use chrono::prelude::Local;
+use serde_json::{json, Value};
+
+// Generic webhook receiving the event as JSON
+pub struct JsonNotifier {
+    url: String,
+}
+
+impl JsonNotifier {
+    pub fn from_config(config: &Value) -> Result<JsonNotifier, String> {
+        Ok(JsonNotifier {
+            url: string(config, "url")?.to_owned(),
+        })
+    }
+}
+
+impl Notifier for JsonNotifier {
+    fn name(&self) -> &'static str {
+        "JSON webhook"
+    }
+
+    fn notify(&self, event: &Event) {
+        let content = json!({
+            "event": event.kind().name(),
+            "title": event.title(),
+            "message": event.text(),
+            "timestamp": Local::now().to_rfc3339(),
+        });
+        let (name, kind, url) = (self.name(), event.kind(), self.url.clone());
+        webhook::in_background(move || {
+            let client = reqwest::blocking::Client::new();
+            let result = webhook::deliver(&client, &url, &content, &RetryPolicy::default());
+            log_delivery(name, kind, result);
+        });
+    }
+}
@@ -0,0 +1,79 @@
+use super::{log_delivery, string, Event, Notifier};
+use crate::webhook::{self, DeliveryError, RetryPolicy};
+use chrono::prelude::Local;
+use reqwest::Url;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+// Matrix room, messages sent with an access token
+pub struct MatrixNotifier {
+    homeserver: Url,
+    room: String,
+    token: String,
+    // Makes transaction IDs unique within a run
+    sent: AtomicU32,
+}
+
+impl MatrixNotifier {
+    pub fn from_config(config: &Value) -> Result<MatrixNotifier, String> {
+        let homeserver = string(config, "homeserver")?;
+        Ok(MatrixNotifier {
+            homeserver: Url::parse(homeserver)
+                .map_err(|error| format!("invalid homeserver '{}': {}", homeserver, error))?,
+            room: string(config, "room")?.to_owned(),
+            token: string(config, "token")?.to_owned(),
+            sent: AtomicU32::new(0),
+        })
+    }
+
+    // Room message endpoint, the transaction ID lets retries be deduplicated
+    fn send_url(&self, transaction: &str) -> Result<Url, DeliveryError> {
+        let mut url = self.homeserver.clone();
+        url.path_segments_mut()
+            .map_err(|_| DeliveryError::Permanent("invalid homeserver URL".to_owned()))?
+            .pop_if_empty()
+            .extend(&[
+                "_matrix",
+                "client",
+                "v3",
+                "rooms",
+                &self.room,
+                "send",
+                "m.room.message",
+                transaction,
+            ]);
+        Ok(url)
+    }
+}
+
+impl Notifier for MatrixNotifier {
+    fn name(&self) -> &'static str {
+        "Matrix"
+    }
+
+    fn notify(&self, event: &Event) {
+        let transaction = format!(
+            "launcher-{}-{}",
+            Local::now().timestamp_millis(),
+            self.sent.fetch_add(1, Ordering::SeqCst)
+        );
+        let content = json!({
+            "msgtype": "m.text",
+            "body": event.text(),
+        });
+        let url = self.send_url(&transaction);
+        let (name, kind, token) = (self.name(), event.kind(), self.token.clone());
+        webhook::in_background(move || {
+            let result = url.and_then(|url| {
+                let client = reqwest::blocking::Client::new();
+                webhook::deliver_request(
+                    &client,
+                    |client| client.put(url.clone()).bearer_auth(&token).json(&content),
+                    &RetryPolicy::default(),
+                )
+                .map(|_| ())
+            });
+            log_delivery(name, kind, result);
+        });
+    }
+}
@@ -0,0 +1,750 @@
+// Notifications about the server, sent to any number of backends
+//
+// The Discord webhook from discord.webhook is used when set. More backends are
+// configured in notifiers.json, an array of objects such as:
+//
+//   [
+//     {"type": "slack", "url": "https://hooks.slack.com/services/...", "events": ["launching", "shutdown", "crash"]},
+//     {"type": "matrix", "homeserver": "https://matrix.org", "room": "!abc:matrix.org", "token": "..."},
+//     {"type": "telegram", "token": "123:abc", "chat_id": "-100123"},
+//     {"type": "webhook", "url": "https://example.com/hook"},
+//     {"type": "email", "server": "smtp.example.com", "username": "...", "password": "...", "from": "...", "to": ["..."]},
+//     {"type": "ntfy", "url": "https://ntfy.sh", "topic": "minecraft"},
+//     {"type": "gotify", "url": "https://gotify.example.com", "token": "..."},
+//     {"type": "discord", "url": "https://discord.com/api/webhooks/...", "thread_id": "123"}
+//   ]
+//
+// "events" limits a backend to some events, all events are sent without it.
+// As it holds tokens, notifiers.json is ignored when other users can read it.
+use crate::context::Context;
+use crate::control;
+use crate::messages;
+use crate::properties;
+use crate::report::SessionReport;
+use crate::secrets;
+use crate::status::Status;
+use crate::webhook;
+use crate::world::WorldInfo;
+use chrono::prelude::{DateTime, Local};
+use chrono::Duration;
+use serde_json::Value;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+mod discord;
+mod email;
+mod json;
+mod matrix;
+mod ntfy;
+mod slack;
+mod telegram;
+
+pub use discord::{check_webhook_url, redact_webhook_url, DiscordNotifier};
+
+// Where additional backends are configured
+const CONFIG_PATH: &str = "notifiers.json";
+
+// Events discord.webhook gets when posting a message for each, the ones it always got
+const DISCORD_WEBHOOK_EVENTS: [EventKind; 8] = [
+    EventKind::Launching,
+    EventKind::Shutdown,
+    EventKind::Crash,
+    EventKind::Hung,
+    EventKind::Restarting,
+    EventKind::NextSession,
+    EventKind::Leaderboard,
+    EventKind::Upgrade,
+];
+
+// Events that only change the status message, when discord.webhook edits one
+const DISCORD_STATUS_EVENTS: [EventKind; 2] = [EventKind::Online, EventKind::Reminder];
+
+// Something worth telling people about
+pub enum Event<'a> {
+    // Server process is starting
+    Launching {
+        level_name: &'a str,
+        server_version: &'a str,
+        server_host: &'a str,
+        shutdown_time: Option<DateTime<Local>>,
+        // None before the world is first generated
+        world: Option<&'a WorldInfo>,
+    },
+    // Server finished loading and accepts players
+    Online {
+        level_name: &'a str,
+        server_version: &'a str,
+        motd: &'a str,
+        max_players: u32,
+    },
+    // Countdown to a shutdown or restart
+    Reminder {
+        message: &'a str,
+        time: DateTime<Local>,
+    },
+    // Session ended normally
+    Shutdown(&'a SessionReport),
+    // Session ended with the server crashing
+    Crash(&'a SessionReport),
+    // Server stopped responding while still running
+    Hung {
+        reason: &'a str,
+        thread_dump: Option<&'a Path>,
+    },
+    // World backup finished
+    Backup {
+        level_name: &'a str,
+        result: &'a Result<PathBuf, String>,
+    },
+    // Server is being restarted within the session
+    Restarting {
+        reason: &'a str,
+    },
+    // Server is offline until the next scheduled session
+    NextSession {
+        start_time: DateTime<Local>,
+        shutdown_time: DateTime<Local>,
+    },
+    // Who played the most over the past week
+    Leaderboard {
+        players: &'a [(String, Duration)],
+    },
+    // Server switched to another Minecraft version
+    Upgrade {
+        level_name: &'a str,
+        from_version: &'a str,
+        to_version: &'a str,
+        backup: &'a Path,
+        result: &'a Result<(), String>,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EventKind {
+    Launching,
+    Online,
+    Reminder,
+    Shutdown,
+    Crash,
+    Hung,
+    Backup,
+    Restarting,
+    NextSession,
+    Leaderboard,
+    Upgrade,
+}
+
+impl EventKind {
+    const ALL: [EventKind; 11] = [
+        EventKind::Launching,
+        EventKind::Online,
+        EventKind::Reminder,
+        EventKind::Shutdown,
+        EventKind::Crash,
+        EventKind::Hung,
+        EventKind::Backup,
+        EventKind::Restarting,
+        EventKind::NextSession,
+        EventKind::Leaderboard,
+        EventKind::Upgrade,
+    ];
+
+    // Name used in event filters
+    pub fn name(self) -> &'static str {
+        match self {
+            EventKind::Launching => "launching",
+            EventKind::Online => "online",
+            EventKind::Reminder => "reminder",
+            EventKind::Shutdown => "shutdown",
+            EventKind::Crash => "crash",
+            EventKind::Hung => "hung",
+            EventKind::Backup => "backup",
+            EventKind::Restarting => "restarting",
+            EventKind::NextSession => "next-session",
+            EventKind::Leaderboard => "leaderboard",
+            EventKind::Upgrade => "upgrade",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<EventKind> {
+        EventKind::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.name() == name)
+    }
+}
+
+impl<'a> Event<'a> {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Event::Launching { .. } => EventKind::Launching,
+            Event::Online { .. } => EventKind::Online,
+            Event::Reminder { .. } => EventKind::Reminder,
+            Event::Shutdown(_) => EventKind::Shutdown,
+            Event::Crash(_) => EventKind::Crash,
+            Event::Hung { .. } => EventKind::Hung,
+            Event::Backup { .. } => EventKind::Backup,
+            Event::Restarting { .. } => EventKind::Restarting,
+            Event::NextSession { .. } => EventKind::NextSession,
+            Event::Leaderboard { .. } => EventKind::Leaderboard,
+            Event::Upgrade { .. } => EventKind::Upgrade,
+        }
+    }
+
+    // One line summary, used as subject or title
+    pub fn title(&self) -> String {
+        match self {
+            Event::Launching {
+                level_name,
+                server_version,
+                server_host,
+                shutdown_time,
+                ..
+            } => messages::format(
+                "event.launching",
+                &[
+                    ("level", level_name),
+                    ("version", server_version),
+                    ("host", server_host),
+                    ("time", &scheduled_time(*shutdown_time)),
+                ],
+            ),
+            Event::Online {
+                level_name,
+                server_version,
+                motd,
+                ..
+            } => messages::format(
+                "event.online",
+                &[
+                    ("level", level_name),
+                    ("version", server_version),
+                    ("motd", motd),
+                ],
+            ),
+            Event::Reminder { message, .. } => (*message).to_owned(),
+            Event::Shutdown(_) => messages::get("event.shutdown"),
+            Event::Crash(_) => messages::get("event.crash"),
+            Event::Hung { .. } => messages::get("event.hung"),
+            Event::Backup { result: Ok(_), .. } => messages::get("event.backup"),
+            Event::Backup { result: Err(_), .. } => messages::get("event.backup-failed"),
+            Event::Restarting { reason } => {
+                messages::format("event.restarting", &[("reason", reason)])
+            }
+            Event::NextSession { .. } => messages::get("event.next-session"),
+            Event::Leaderboard { .. } => messages::get("event.leaderboard"),
+            Event::Upgrade {
+                from_version,
+                to_version,
+                result,
+                ..
+            } => messages::format(
+                match result {
+                    Ok(()) => "event.upgrade",
+                    Err(_) => "event.upgrade-failed",
+                },
+                &[("from", from_version), ("to", to_version)],
+            ),
+        }
+    }
+
+    // Labelled details, such as ("Level Name:", "world")
+    pub fn fields(&self) -> Vec<(String, String)> {
+        let field = |key: &str, value: String| (messages::get(key), value);
+        match self {
+            Event::Launching {
+                level_name,
+                server_version,
+                server_host,
+                shutdown_time,
+                world,
+            } => {
+                let mut fields = vec![
+                    field("field.level-name", level_name.to_string()),
+                    field("field.version", server_version.to_string()),
+                    field("field.host", server_host.to_string()),
+                    field("field.shutdown-time", scheduled_time(*shutdown_time)),
+                ];
+                fields.extend(world.map(WorldInfo::fields).unwrap_or_default());
+                fields
+            }
+            Event::Online {
+                level_name,
+                server_version,
+                motd,
+                max_players,
+            } => vec![
+                field("field.level-name", level_name.to_string()),
+                field("field.version", server_version.to_string()),
+                field("field.motd", motd.to_string()),
+                field("field.max-players", max_players.to_string()),
+            ],
+            Event::Reminder { time, .. } => vec![field("field.scheduled-for", time.to_string())],
+            Event::Shutdown(report) | Event::Crash(report) => vec![
+                field("field.reason", report.reason.label()),
+                field("field.exit-status", report.exit_status()),
+                field(
+                    "field.duration",
+                    control::format_duration(report.end_time - report.start_time),
+                ),
+                field("field.peak-players", report.peak_players.to_string()),
+                field(
+                    "field.players",
+                    if report.joined_players.is_empty() {
+                        messages::get("field.nobody")
+                    } else {
+                        report
+                            .playtimes()
+                            .iter()
+                            .map(|(player, time)| {
+                                format!("{} ({})", player, control::format_duration(*time))
+                            })
+                            .collect::<Vec<String>>()
+                            .join(", ")
+                    },
+                ),
+            ],
+            Event::Hung {
+                reason,
+                thread_dump,
+            } => vec![
+                field("field.reason", reason.to_string()),
+                field(
+                    "field.thread-dump",
+                    match thread_dump {
+                        Some(path) => path.display().to_string(),
+                        None => messages::get("field.not-saved"),
+                    },
+                ),
+            ],
+            Event::Backup { level_name, result } => vec![
+                field("field.level-name", level_name.to_string()),
+                match result {
+                    Ok(path) => field("field.saved-to", path.display().to_string()),
+                    Err(error) => field("field.error", error.clone()),
+                },
+            ],
+            Event::Restarting { .. } => Vec::new(),
+            Event::NextSession {
+                start_time,
+                shutdown_time,
+            } => vec![
+                field("field.starts", start_time.to_string()),
+                field("field.shutdown-time", shutdown_time.to_string()),
+            ],
+            Event::Leaderboard { players: [] } => {
+                vec![field("field.players", messages::get("field.nobody"))]
+            }
+            Event::Leaderboard { players } => players
+                .iter()
+                .enumerate()
+                .map(|(rank, (player, time))| {
+                    (
+                        format!("{}. {}", rank + 1, player),
+                        control::format_duration(*time),
+                    )
+                })
+                .collect(),
+            Event::Upgrade {
+                level_name,
+                backup,
+                result,
+                ..
+            } => {
+                let mut fields = vec![
+                    field("field.level-name", level_name.to_string()),
+                    field("field.backup", backup.display().to_string()),
+                ];
+                if let Err(error) = result {
+                    fields.push(field("field.error", error.clone()));
+                }
+                fields
+            }
+        }
+    }
+
+    // Plain text for backends without rich formatting
+    pub fn text(&self) -> String {
+        let mut text = self.title();
+        for (label, value) in self.fields() {
+            text += &format!("\n{} {}", label, value);
+        }
+        text
+    }
+}
+
+// Shutdown time, or a note that there's none
+pub fn scheduled_time(shutdown_time: Option<DateTime<Local>>) -> String {
+    match shutdown_time {
+        Some(shutdown_time) => format!("{}", shutdown_time),
+        None => messages::get("field.not-scheduled"),
+    }
+}
+
+// A place notifications are sent to
+pub trait Notifier {
+    // Backend name for log messages
+    fn name(&self) -> &'static str;
+    // Deliver the event through webhook::in_background, so a slow or failing
+    // backend never holds up the session, failures are logged by the backend
+    fn notify(&self, event: &Event);
+    // Called every second while the server runs, for backends showing live status
+    fn update(&self, _status: &Status) {}
+}
+
+// Backend with the events it wants
+struct Backend {
+    notifier: Box<dyn Notifier>,
+    events: Option<Vec<EventKind>>,
+}
+
+// Every configured backend
+pub struct Notifiers {
+    backends: Vec<Backend>,
+}
+
+impl Notifiers {
+    // Nothing gets notified, for --no-notify
+    pub fn none() -> Notifiers {
+        Notifiers {
+            backends: Vec::new(),
+        }
+    }
+
+    // Discord webhook, when there's one, plus whatever notifiers.json configures
+    pub fn load(context: &Context, app_name: &str, discord_webhook_url: Option<&str>) -> Notifiers {
+        // Send messages left over by previous runs
+        let queue = context.path(webhook::QUEUE_PATH);
+        let fallback_url = discord_webhook_url.map(str::to_owned);
+        webhook::in_background(move || webhook::flush_queue(&queue, fallback_url.as_deref()));
+
+        let mut backends = Vec::new();
+        if let Some(discord_webhook_url) = discord_webhook_url {
+            // Status message edits need every session event, posts only some
+            let contents =
+                fs::read_to_string(context.path("server.properties")).unwrap_or_default();
+            let discord = DiscordNotifier::new(
+                context,
+                app_name,
+                discord_webhook_url,
+                properties::get(&contents, "discord-thread-id"),
+            );
+
+            let mut events = DISCORD_WEBHOOK_EVENTS.to_vec();
+            if discord.edits_messages() {
+                events.extend(&DISCORD_STATUS_EVENTS);
+            }
+            backends.push(Backend {
+                notifier: Box::new(discord),
+                events: Some(events),
+            });
+        }
+
+        let contents = match fs::read_to_string(context.path(CONFIG_PATH)) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Notifiers { backends },
+            Err(error) => {
+                println!("[ERROR] Failed to read {}: {}", CONFIG_PATH, error);
+                return Notifiers { backends };
+            }
+        };
+        // Backends may hold tokens, like credentials.properties
+        if !secrets::private(&context.path(CONFIG_PATH)) {
+            println!(
+                "[ERROR] Ignoring {} as other users can read it, run `chmod 600 {}`",
+                CONFIG_PATH, CONFIG_PATH
+            );
+            return Notifiers { backends };
+        }
+        let entries = match serde_json::from_str::<Value>(&contents) {
+            Ok(Value::Array(entries)) => entries,
+            Ok(_) => {
+                println!("[ERROR] {} must hold an array of backends", CONFIG_PATH);
+                return Notifiers { backends };
+            }
+            Err(error) => {
+                println!("[ERROR] Failed to parse {}: {}", CONFIG_PATH, error);
+                return Notifiers { backends };
+            }
+        };
+
+        for (index, entry) in entries.iter().enumerate() {
+            match Backend::from_config(context, app_name, entry) {
+                Ok(backend) => {
+                    println!(
+                        "[INFO] Notifying {} of {}",
+                        backend.notifier.name(),
+                        match &backend.events {
+                            Some(events) => events
+                                .iter()
+                                .map(|kind| kind.name())
+                                .collect::<Vec<&str>>()
+                                .join(", "),
+                            None => "all events".to_owned(),
+                        }
+                    );
+                    backends.push(backend)
+                }
+                Err(error) => println!(
+                    "[ERROR] Skipping backend {} in {}: {}",
+                    index + 1,
+                    CONFIG_PATH,
+                    error
+                ),
+            }
+        }
+        Notifiers { backends }
+    }
+
+    // Let backends refresh live status
+    pub fn update(&self, status: &Status) {
+        for backend in &self.backends {
+            backend.notifier.update(status);
+        }
+    }
+
+    // Send an event to every backend that wants it
+    pub fn notify(&self, event: &Event) {
+        let kind = event.kind();
+        for backend in &self.backends {
+            if backend
+                .events
+                .as_ref()
+                .is_none_or(|events| events.contains(&kind))
+            {
+                backend.notifier.notify(event);
+            }
+        }
+    }
+}
+
+// Notifications still being delivered get a chance to go out before the launcher exits
+impl Drop for Notifiers {
+    fn drop(&mut self) {
+        webhook::wait_for_deliveries();
+    }
+}
+
+impl Backend {
+    fn from_config(context: &Context, app_name: &str, config: &Value) -> Result<Backend, String> {
+        let notifier: Box<dyn Notifier> = match config["type"].as_str() {
+            Some("discord") => Box::new(DiscordNotifier::new(
+                context,
+                app_name,
+                string(config, "url")?,
+                config["thread_id"].as_str(),
+            )),
+            Some("slack") => Box::new(slack::SlackNotifier::from_config(config)?),
+            Some("matrix") => Box::new(matrix::MatrixNotifier::from_config(config)?),
+            Some("telegram") => Box::new(telegram::TelegramNotifier::from_config(config)?),
+            Some("webhook") => Box::new(json::JsonNotifier::from_config(config)?),
+            Some("email") => Box::new(email::EmailNotifier::from_config(config)?),
+            Some("ntfy") => Box::new(ntfy::NtfyNotifier::from_config(config)?),
+            Some("gotify") => Box::new(ntfy::GotifyNotifier::from_config(config)?),
+            Some(other) => return Err(format!("unknown type '{}'", other)),
+            None => return Err("missing type".to_owned()),
+        };
+        let events = match &config["events"] {
+            Value::Null => None,
+            Value::Array(names) => {
+                let mut events = Vec::new();
+                for name in names {
+                    match name.as_str().and_then(EventKind::parse) {
+                        Some(kind) => events.push(kind),
+                        None => return Err(format!("unknown event {}", name)),
+                    }
+                }
+                Some(events)
+            }
+            _ => return Err("events must be an array".to_owned()),
+        };
+        Ok(Backend { notifier, events })
+    }
+}
+
+// Read a required string setting
+fn string<'a>(config: &'a Value, key: &str) -> Result<&'a str, String> {
+    config[key]
+        .as_str()
+        .ok_or_else(|| format!("missing {}", key))
+}
+
+// Log how a delivery went
+fn log_delivery(backend: &str, kind: EventKind, result: Result<(), webhook::DeliveryError>) {
+    match result {
+        Ok(()) => println!("[INFO] Sent {} notification to {}", kind.name(), backend),
+        Err(error) => println!(
+            "[ERROR] Failed to send {} notification to {}: {}",
+            kind.name(),
+            backend,
+            error
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub::{Request, Stub};
+    use serde_json::json;
+    use std::env;
+    use std::os::unix::fs::PermissionsExt;
+    use std::process;
+
+    fn hung() -> Event<'static> {
+        Event::Hung {
+            reason: "no ticks for 60s",
+            thread_dump: None,
+        }
+    }
+
+    // Send the event to a backend configured with the stub URL
+    fn deliver(config: Value, event: &Event) -> Request {
+        let stub = Stub::start(Vec::new());
+        let config = serde_json::to_string(&config)
+            .unwrap()
+            .replace("STUB", &stub.url);
+        let backend = Backend::from_config(
+            &Context::default(),
+            "Test",
+            &serde_json::from_str(&config).unwrap(),
+        )
+        .unwrap();
+        backend.notifier.notify(event);
+        stub.next_request()
+    }
+
+    fn header<'a>(request: &'a Request, name: &str) -> Option<&'a str> {
+        request
+            .headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn body(request: &Request) -> Value {
+        serde_json::from_str(&request.body).unwrap()
+    }
+
+    #[test]
+    fn posts_slack_blocks() {
+        let request = deliver(json!({"type": "slack", "url": "STUB/services/T0"}), &hung());
+        assert_eq!(
+            (request.method.as_str(), request.path.as_str()),
+            ("POST", "/services/T0")
+        );
+        assert_eq!(
+            body(&request),
+            json!({
+                "text": hung().title(),
+                "blocks": [{"type": "section", "text": {"type": "mrkdwn", "text": hung().text()}}]
+            })
+        );
+    }
+
+    #[test]
+    fn posts_the_event_as_json() {
+        let request = deliver(json!({"type": "webhook", "url": "STUB/hook"}), &hung());
+        let content = body(&request);
+        assert_eq!(request.path, "/hook");
+        assert_eq!(content["event"], "hung");
+        assert_eq!(content["title"], hung().title());
+        assert_eq!(content["message"], hung().text());
+        assert!(DateTime::parse_from_rfc3339(content["timestamp"].as_str().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn puts_matrix_messages() {
+        let request = deliver(
+            json!({"type": "matrix", "homeserver": "STUB/", "room": "!abc:example.org", "token": "secret"}),
+            &hung(),
+        );
+        assert_eq!(request.method, "PUT");
+        assert!(request.path.starts_with(
+            "/_matrix/client/v3/rooms/!abc:example.org/send/m.room.message/launcher-"
+        ));
+        assert_eq!(header(&request, "authorization"), Some("Bearer secret"));
+        assert_eq!(
+            body(&request),
+            json!({"msgtype": "m.text", "body": hung().text()})
+        );
+    }
+
+    #[test]
+    fn sends_telegram_messages() {
+        let request = deliver(
+            json!({"type": "telegram", "api_url": "STUB", "token": "123:abc", "chat_id": -100123}),
+            &hung(),
+        );
+        assert_eq!(request.path, "/bot123:abc/sendMessage");
+        assert_eq!(
+            body(&request),
+            json!({"chat_id": -100123, "text": hung().text()})
+        );
+    }
+
+    #[test]
+    fn publishes_to_ntfy_and_gotify() {
+        let request = deliver(
+            json!({"type": "ntfy", "url": "STUB", "topic": "minecraft", "token": "secret"}),
+            &hung(),
+        );
+        assert_eq!(request.path, "/minecraft");
+        assert_eq!(header(&request, "tags"), Some("hung"));
+        assert_eq!(header(&request, "authorization"), Some("Bearer secret"));
+        assert_eq!(request.body, hung().text());
+
+        let request = deliver(
+            json!({"type": "gotify", "url": "STUB/", "token": "secret", "priority": 8}),
+            &hung(),
+        );
+        assert_eq!(request.path, "/message");
+        assert_eq!(header(&request, "x-gotify-key"), Some("secret"));
+        assert_eq!(
+            body(&request),
+            json!({"title": hung().title(), "message": hung().text(), "priority": 8})
+        );
+    }
+
+    #[test]
+    fn sends_backends_the_events_they_want() {
+        let stub = Stub::start(Vec::new());
+        let config = json!({"type": "webhook", "url": stub.url, "events": ["crash", "hung"]});
+        let notifiers = Notifiers {
+            backends: vec![Backend::from_config(&Context::default(), "Test", &config).unwrap()],
+        };
+        notifiers.notify(&Event::Restarting { reason: "lag" });
+        notifiers.notify(&hung());
+        assert_eq!(body(&stub.next_request())["event"], "hung");
+        stub.assert_no_more_requests();
+
+        let config = json!({"type": "webhook", "url": stub.url, "events": ["lunch"]});
+        assert_eq!(
+            Backend::from_config(&Context::default(), "Test", &config).err(),
+            Some("unknown event \"lunch\"".to_owned())
+        );
+    }
+
+    #[test]
+    fn ignores_config_other_users_can_read() {
+        let root = env::temp_dir().join(format!("launcher-notifiers-{}", process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let path = root.join(CONFIG_PATH);
+        fs::write(
+            &path,
+            r#"[{"type": "webhook", "url": "http://127.0.0.1:1/token"}]"#,
+        )
+        .unwrap();
+        let context = Context {
+            root: root.clone(),
+            ..Context::default()
+        };
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(Notifiers::load(&context, "Test", None).backends.is_empty());
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        assert_eq!(Notifiers::load(&context, "Test", None).backends.len(), 1);
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,106 @@
+use super::{log_delivery, string, Event, Notifier};
+use crate::webhook::{self, RetryPolicy};
+use serde_json::{json, Value};
+
+// ntfy topic, optionally protected by an access token
+pub struct NtfyNotifier {
+    url: String,
+    token: Option<String>,
+}
+
+impl NtfyNotifier {
+    pub fn from_config(config: &Value) -> Result<NtfyNotifier, String> {
+        Ok(NtfyNotifier {
+            url: format!(
+                "{}/{}",
+                config["url"]
+                    .as_str()
+                    .unwrap_or("https://ntfy.sh")
+                    .trim_end_matches('/'),
+                string(config, "topic")?
+            ),
+            token: config["token"].as_str().map(str::to_owned),
+        })
+    }
+}
+
+impl Notifier for NtfyNotifier {
+    fn name(&self) -> &'static str {
+        "ntfy"
+    }
+
+    fn notify(&self, event: &Event) {
+        let title = event.title();
+        let text = event.text();
+        let (name, kind) = (self.name(), event.kind());
+        let (url, token) = (self.url.clone(), self.token.clone());
+        webhook::in_background(move || {
+            let client = reqwest::blocking::Client::new();
+            let result = webhook::deliver_request(
+                &client,
+                |client| {
+                    let request = client
+                        .post(&url)
+                        .header("Title", &title)
+                        .header("Tags", kind.name())
+                        .body(text.clone());
+                    match &token {
+                        Some(token) => request.bearer_auth(token),
+                        None => request,
+                    }
+                },
+                &RetryPolicy::default(),
+            )
+            .map(|_| ());
+            log_delivery(name, kind, result);
+        });
+    }
+}
+
+// Gotify server, messages sent with an application token
+pub struct GotifyNotifier {
+    url: String,
+    token: String,
+    priority: u64,
+}
+
+impl GotifyNotifier {
+    pub fn from_config(config: &Value) -> Result<GotifyNotifier, String> {
+        Ok(GotifyNotifier {
+            url: format!("{}/message", string(config, "url")?.trim_end_matches('/')),
+            token: string(config, "token")?.to_owned(),
+            priority: config["priority"].as_u64().unwrap_or(5),
+        })
+    }
+}
+
+impl Notifier for GotifyNotifier {
+    fn name(&self) -> &'static str {
+        "Gotify"
+    }
+
+    fn notify(&self, event: &Event) {
+        let content = json!({
+            "title": event.title(),
+            "message": event.text(),
+            "priority": self.priority,
+        });
+        let (name, kind) = (self.name(), event.kind());
+        let (url, token) = (self.url.clone(), self.token.clone());
+        webhook::in_background(move || {
+            let client = reqwest::blocking::Client::new();
+            let result = webhook::deliver_request(
+                &client,
+                |client| {
+                    client
+                        .post(&url)
+                        .header("X-Gotify-Key", &token)
+                        .json(&content)
+                },
+                &RetryPolicy::default(),
+            )
+            .map(|_| ());
+            log_delivery(name, kind, result);
+        });
+    }
+}
@@ -0,0 +1,38 @@
+use super::{log_delivery, string, Event, Notifier};
+use crate::webhook::{self, RetryPolicy};
+use serde_json::{json, Value};
+
+// Slack incoming webhook
+pub struct SlackNotifier {
+    url: String,
+}
+
+impl SlackNotifier {
+    pub fn from_config(config: &Value) -> Result<SlackNotifier, String> {
+        Ok(SlackNotifier {
+            url: string(config, "url")?.to_owned(),
+        })
+    }
+}
+
+impl Notifier for SlackNotifier {
+    fn name(&self) -> &'static str {
+        "Slack"
+    }
+
+    fn notify(&self, event: &Event) {
+        let content = json!({
+            "text": event.title(),
+            "blocks": [{
+                "type": "section",
+                "text": {"type": "mrkdwn", "text": event.text()}
+            }]
+        });
+        let (name, kind, url) = (self.name(), event.kind(), self.url.clone());
+        webhook::in_background(move || {
+            let client = reqwest::blocking::Client::new();
+            let result = webhook::deliver(&client, &url, &content, &RetryPolicy::default());
+            log_delivery(name, kind, result);
+        });
+    }
+}
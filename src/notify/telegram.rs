@@ -0,0 +1,48 @@
+use super::{log_delivery, string, Event, Notifier};
+use crate::webhook::{self, RetryPolicy};
+use serde_json::{json, Value};
+
+// Telegram Bot API, messages sent by a bot to a chat
+pub struct TelegramNotifier {
+    api_url: String,
+    token: String,
+    chat_id: Value,
+}
+
+impl TelegramNotifier {
+    pub fn from_config(config: &Value) -> Result<TelegramNotifier, String> {
+        let chat_id = match &config["chat_id"] {
+            Value::Null => return Err("missing chat_id".to_owned()),
+            chat_id => chat_id.clone(),
+        };
+        Ok(TelegramNotifier {
+            api_url: config["api_url"]
+                .as_str()
+                .unwrap_or("https://api.telegram.org")
+                .trim_end_matches('/')
+                .to_owned(),
+            token: string(config, "token")?.to_owned(),
+            chat_id,
+        })
+    }
+}
+
+impl Notifier for TelegramNotifier {
+    fn name(&self) -> &'static str {
+        "Telegram"
+    }
+
+    fn notify(&self, event: &Event) {
+        let url = format!("{}/bot{}/sendMessage", self.api_url, self.token);
+        let content = json!({
+            "chat_id": self.chat_id,
+            "text": event.text(),
+        });
+        let (name, kind) = (self.name(), event.kind());
+        webhook::in_background(move || {
+            let client = reqwest::blocking::Client::new();
+            let result = webhook::deliver(&client, &url, &content, &RetryPolicy::default());
+            log_delivery(name, kind, result);
+        });
+    }
+}
@@ -0,0 +1,177 @@
+use crate::ping;
+use regex::Regex;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader};
+use std::process::ChildStdout;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+
+// Things happening on the server, parsed from its console output
+#[derive(Debug, PartialEq)]
+pub enum ServerEvent {
+    Chat { player: String, message: String },
+    Joined(String),
+    Left(String),
+    // Server finished loading the world
+    Online,
+    // Server logged "Can't keep up!", milliseconds behind
+    Lagging(u64),
+    // Ticks per second, printed by a TPS command
+    Tps(f64),
+    // Any other line, the server is still writing
+    Output,
+}
+
+// Console lines kept for the dashboard
+const HISTORY_LINES: usize = 500;
+
+// Recent console lines, numbered so readers can pick up where they left off
+static HISTORY: Mutex<History> = Mutex::new(History {
+    next: 0,
+    lines: VecDeque::new(),
+});
+
+struct History {
+    // Number the next line gets
+    next: u64,
+    lines: VecDeque<String>,
+}
+
+// Echo server output to the launcher console and parse it on a background thread
+pub fn spawn(stdout: ChildStdout) -> Receiver<ServerEvent> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || read_output(stdout, sender));
+    receiver
+}
+
+// Read server output until the server closes it
+fn read_output(stdout: ChildStdout, sender: Sender<ServerEvent>) {
+    let parser = Parser::new();
+    for line in BufReader::new(stdout).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(error) => {
+                println!("[WARN] Failed to read server output: {}", error);
+                break;
+            }
+        };
+        println!("{}", line);
+        remember(&line);
+        // Keep echoing even if nobody is listening anymore
+        let _ = sender.send(parser.parse(&line).unwrap_or(ServerEvent::Output));
+    }
+}
+
+fn remember(line: &str) {
+    let mut history = HISTORY
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    history.next += 1;
+    history.lines.push_back(line.to_owned());
+    if history.lines.len() > HISTORY_LINES {
+        history.lines.pop_front();
+    }
+}
+
+// Console lines numbered from the given one on, with the number to ask for next
+pub fn lines_since(number: u64) -> (Vec<String>, u64) {
+    let history = HISTORY
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let first = history.next - history.lines.len() as u64;
+    let skip = number.saturating_sub(first) as usize;
+    (
+        history.lines.iter().skip(skip).cloned().collect(),
+        history.next,
+    )
+}
+
+struct Parser {
+    info: Regex,
+    warn: Regex,
+    lagging: Regex,
+    chat: Regex,
+    joined: Regex,
+    left: Regex,
+    online: Regex,
+}
+
+impl Parser {
+    fn new() -> Parser {
+        Parser {
+            info: Regex::new(r"^\[.*?INFO\]: (.*)$").unwrap(),
+            warn: Regex::new(r"^\[.*?WARN\]: (.*)$").unwrap(),
+            lagging: Regex::new(r"^Can't keep up!.* Running (\d+)ms").unwrap(),
+            // 1.19.1 and later mark messages without a chat signature
+            chat: Regex::new(r"^(?:\[Not Secure\] )?<([^>]+)> (.*)$").unwrap(),
+            joined: Regex::new(r"^(\w+) joined the game$").unwrap(),
+            left: Regex::new(r"^(\w+) left the game$").unwrap(),
+            online: Regex::new(r"^Done \(.*\)! For help").unwrap(),
+        }
+    }
+
+    fn parse(&self, line: &str) -> Option<ServerEvent> {
+        if let Some(captures) = self.warn.captures(line) {
+            let captures = self.lagging.captures(captures.get(1)?.as_str())?;
+            return captures[1].parse().ok().map(ServerEvent::Lagging);
+        }
+        let text = self.info.captures(line)?.get(1)?.as_str();
+        if let Some(captures) = self.chat.captures(text) {
+            Some(ServerEvent::Chat {
+                player: captures[1].to_owned(),
+                message: captures[2].to_owned(),
+            })
+        } else if let Some(captures) = self.joined.captures(text) {
+            Some(ServerEvent::Joined(captures[1].to_owned()))
+        } else if let Some(captures) = self.left.captures(text) {
+            Some(ServerEvent::Left(captures[1].to_owned()))
+        } else if self.online.is_match(text) {
+            Some(ServerEvent::Online)
+        } else {
+            parse_tps(text).map(ServerEvent::Tps)
+        }
+    }
+}
+
+// Ticks per second over the last minute, from Paper's /tps or /forge tps output
+pub fn parse_tps(text: &str) -> Option<f64> {
+    static TPS: OnceLock<Regex> = OnceLock::new();
+    let tps = TPS.get_or_init(|| {
+        Regex::new(r"(?:TPS from last 1m, 5m, 15m: \*?|Mean TPS: )([\d.]+)").unwrap()
+    });
+    tps.captures(&ping::strip_formatting(text))?[1].parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_chat_messages() {
+        let parser = Parser::new();
+        let chat = |player: &str, message: &str| {
+            Some(ServerEvent::Chat {
+                player: player.to_owned(),
+                message: message.to_owned(),
+            })
+        };
+        assert_eq!(
+            parser.parse("[12:00:03] [Server thread/INFO]: <Steve> !extend"),
+            chat("Steve", "!extend")
+        );
+        assert_eq!(
+            parser.parse("[12:00:03] [Server thread/INFO]: [Not Secure] <Alex> hi <3"),
+            chat("Alex", "hi <3")
+        );
+        assert_eq!(
+            parser.parse("[12:00:04] [Server thread/INFO]: Steve joined the game"),
+            Some(ServerEvent::Joined("Steve".to_owned()))
+        );
+        // Only INFO lines are chat
+        assert_eq!(
+            parser.parse("[12:00:05] [Server thread/WARN]: <Steve> !extend"),
+            None
+        );
+    }
+}
@@ -0,0 +1,287 @@
+// Server List Ping, the status request the multiplayer screen sends
+use crate::properties;
+use chrono::prelude::Local;
+use serde_json::Value;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+// Protocol version sent in the handshake, -1 asks without caring about compatibility
+const PROTOCOL_VERSION: i32 = -1;
+
+// Largest response accepted, status with a favicon is well under this
+const MAX_PACKET_LENGTH: usize = 2 * 1024 * 1024;
+
+// What the server reports about itself
+#[derive(Clone)]
+pub struct ServerStatus {
+    pub version: String,
+    pub protocol: i64,
+    // Description with formatting codes removed
+    pub motd: String,
+    pub online_players: u32,
+    pub max_players: u32,
+    // Some of the online players, servers may leave it empty
+    pub sample: Vec<String>,
+    // Every online player, when the probe lists them all
+    pub players: Option<Vec<String>>,
+    pub latency: Duration,
+}
+
+// Address the launcher pings, from server-ip and server-port in server.properties
+pub fn address(contents: &str) -> (String, u16) {
+    let host = match properties::get(contents, "server-ip") {
+        Some(host) if !host.is_empty() => host.to_owned(),
+        _ => "127.0.0.1".to_owned(),
+    };
+    (host, properties::get_number(contents, "server-port", 25565))
+}
+
+// Ask the server for its status, then measure the round trip
+pub fn ping(host: &str, port: u16, timeout: Duration) -> io::Result<ServerStatus> {
+    let address = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| invalid(format!("no address for {}", host)))?;
+    let mut stream = TcpStream::connect_timeout(&address, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    // Handshake, next state 1 is status
+    let mut handshake = Vec::new();
+    write_varint(&mut handshake, PROTOCOL_VERSION);
+    write_string(&mut handshake, host);
+    handshake.extend_from_slice(&port.to_be_bytes());
+    write_varint(&mut handshake, 1);
+    stream.write_all(&packet(0x00, &handshake))?;
+
+    // Status request
+    stream.write_all(&packet(0x00, &[]))?;
+    let (id, payload) = read_packet(&mut stream)?;
+    if id != 0x00 {
+        return Err(invalid(format!("unexpected packet {:#04x}", id)));
+    }
+    let json = read_string(&mut &payload[..])?;
+
+    // Ping, the server echoes the payload back
+    let token = Local::now().timestamp_millis();
+    let sent = Instant::now();
+    stream.write_all(&packet(0x01, &token.to_be_bytes()))?;
+    let (id, payload) = read_packet(&mut stream)?;
+    if id != 0x01 || payload != token.to_be_bytes() {
+        return Err(invalid("unexpected pong".to_owned()));
+    }
+    let latency = sent.elapsed();
+
+    parse_status(&json, latency)
+}
+
+fn parse_status(json: &str, latency: Duration) -> io::Result<ServerStatus> {
+    let status = serde_json::from_str::<Value>(json)
+        .map_err(|error| invalid(format!("invalid status: {}", error)))?;
+    let mut motd = String::new();
+    flatten_text(&status["description"], &mut motd);
+    Ok(ServerStatus {
+        version: status["version"]["name"]
+            .as_str()
+            .unwrap_or_default()
+            .to_owned(),
+        protocol: status["version"]["protocol"].as_i64().unwrap_or(-1),
+        motd: strip_formatting(&motd),
+        online_players: status["players"]["online"].as_u64().unwrap_or(0) as u32,
+        max_players: status["players"]["max"].as_u64().unwrap_or(0) as u32,
+        sample: status["players"]["sample"]
+            .as_array()
+            .map(|sample| {
+                sample
+                    .iter()
+                    .filter_map(|player| player["name"].as_str().map(str::to_owned))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        players: None,
+        latency,
+    })
+}
+
+// Descriptions are plain strings or chat components with nested "extra" parts
+fn flatten_text(component: &Value, text: &mut String) {
+    match component {
+        Value::String(part) => text.push_str(part),
+        Value::Array(parts) => parts.iter().for_each(|part| flatten_text(part, text)),
+        Value::Object(_) => {
+            flatten_text(&component["text"], text);
+            flatten_text(&component["extra"], text);
+        }
+        _ => (),
+    }
+}
+
+// Remove "§a" style colour and formatting codes
+pub fn strip_formatting(text: &str) -> String {
+    let mut stripped = String::new();
+    let mut characters = text.chars();
+    while let Some(character) = characters.next() {
+        if character == '\u{a7}' {
+            characters.next();
+        } else {
+            stripped.push(character);
+        }
+    }
+    stripped
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+// Length-prefixed packet with its ID
+fn packet(id: i32, payload: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+    write_varint(&mut body, id);
+    body.extend_from_slice(payload);
+    let mut packet = Vec::new();
+    write_varint(&mut packet, body.len() as i32);
+    packet.extend(body);
+    packet
+}
+
+fn read_packet(reader: &mut impl Read) -> io::Result<(i32, Vec<u8>)> {
+    let length = read_varint(reader)?;
+    if length <= 0 || length as usize > MAX_PACKET_LENGTH {
+        return Err(invalid(format!("invalid packet length {}", length)));
+    }
+    let mut body = vec![0; length as usize];
+    reader.read_exact(&mut body)?;
+    let mut body = &body[..];
+    let id = read_varint(&mut body)?;
+    Ok((id, body.to_vec()))
+}
+
+fn write_varint(buffer: &mut Vec<u8>, value: i32) {
+    let mut value = value as u32;
+    loop {
+        if value & !0x7f == 0 {
+            buffer.push(value as u8);
+            return;
+        }
+        buffer.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+}
+
+fn read_varint(reader: &mut impl Read) -> io::Result<i32> {
+    let mut value = 0u32;
+    for position in 0..5 {
+        let mut byte = [0];
+        reader.read_exact(&mut byte)?;
+        value |= ((byte[0] & 0x7f) as u32) << (7 * position);
+        if byte[0] & 0x80 == 0 {
+            return Ok(value as i32);
+        }
+    }
+    Err(invalid("VarInt too long".to_owned()))
+}
+
+fn write_string(buffer: &mut Vec<u8>, text: &str) {
+    write_varint(buffer, text.len() as i32);
+    buffer.extend_from_slice(text.as_bytes());
+}
+
+fn read_string(reader: &mut impl Read) -> io::Result<String> {
+    let length = read_varint(reader)?;
+    if length < 0 || length as usize > MAX_PACKET_LENGTH {
+        return Err(invalid(format!("invalid string length {}", length)));
+    }
+    let mut bytes = vec![0; length as usize];
+    reader.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|error| invalid(error.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    // Answer one status request like a server would, returning the handshake
+    fn fake_server(status: &'static str) -> (u16, thread::JoinHandle<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let (id, handshake) = read_packet(&mut stream).unwrap();
+            assert_eq!(id, 0x00);
+            let (id, request) = read_packet(&mut stream).unwrap();
+            assert_eq!((id, request.len()), (0x00, 0));
+
+            let mut response = Vec::new();
+            write_string(&mut response, status);
+            stream.write_all(&packet(0x00, &response)).unwrap();
+
+            let (id, payload) = read_packet(&mut stream).unwrap();
+            assert_eq!(id, 0x01);
+            stream.write_all(&packet(0x01, &payload)).unwrap();
+            handshake
+        });
+        (port, handle)
+    }
+
+    #[test]
+    fn reads_status_from_server() {
+        let (port, server) = fake_server(
+            r#"{"version":{"name":"1.16.5","protocol":754},"players":{"max":20,"online":2,"sample":[{"name":"Steve","id":"0"},{"name":"Alex","id":"1"}]},"description":{"text":"§3A shelter","extra":[{"text":" §6for everyone"}]}}"#,
+        );
+        let status = ping("127.0.0.1", port, Duration::from_secs(5)).unwrap();
+
+        assert_eq!(status.version, "1.16.5");
+        assert_eq!(status.protocol, 754);
+        assert_eq!(status.motd, "A shelter for everyone");
+        assert_eq!((status.online_players, status.max_players), (2, 20));
+        assert_eq!(status.sample, vec!["Steve", "Alex"]);
+
+        // Handshake carries the protocol version, host, port and status state
+        let handshake = server.join().unwrap();
+        let mut reader = &handshake[..];
+        assert_eq!(read_varint(&mut reader).unwrap(), PROTOCOL_VERSION);
+        assert_eq!(read_string(&mut reader).unwrap(), "127.0.0.1");
+        let mut port_bytes = [0; 2];
+        reader.read_exact(&mut port_bytes).unwrap();
+        assert_eq!(u16::from_be_bytes(port_bytes), port);
+        assert_eq!(read_varint(&mut reader).unwrap(), 1);
+    }
+
+    #[test]
+    fn accepts_plain_string_description() {
+        let (port, server) = fake_server(
+            r#"{"version":{"name":"Paper 1.20.1","protocol":763},"players":{"max":10,"online":0},"description":"Hello"}"#,
+        );
+        let status = ping("127.0.0.1", port, Duration::from_secs(5)).unwrap();
+        server.join().unwrap();
+
+        assert_eq!(status.motd, "Hello");
+        assert!(status.sample.is_empty());
+    }
+
+    #[test]
+    fn fails_when_nothing_listens() {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        assert!(ping("127.0.0.1", port, Duration::from_secs(1)).is_err());
+    }
+
+    #[test]
+    fn encodes_varints() {
+        for value in [0, 1, 127, 128, 255, 25565, 2097151, i32::MAX, -1] {
+            let mut buffer = Vec::new();
+            write_varint(&mut buffer, value);
+            assert_eq!(read_varint(&mut &buffer[..]).unwrap(), value);
+        }
+        let mut buffer = Vec::new();
+        write_varint(&mut buffer, -1);
+        assert_eq!(buffer.len(), 5);
+    }
+}
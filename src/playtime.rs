@@ -0,0 +1,300 @@
+// Player playtime across sessions, kept in playtime.json as:
+//
+//   {
+//     "players": {
+//       "Steve": {"total_seconds": 7200, "sessions": 3, "last_seen": "...", "days": {"2021-05-01": 3600}}
+//     },
+//     "last_leaderboard": "..."
+//   }
+//
+// Days older than DAYS_KEPT are dropped, totals are kept forever.
+use crate::context::Context;
+use crate::control;
+use crate::error::LauncherError;
+use crate::properties;
+use crate::report::SessionReport;
+use chrono::prelude::{DateTime, Local, TimeZone};
+use chrono::{Duration, NaiveDate};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::fs;
+use std::io::ErrorKind;
+
+const PLAYTIME_PATH: &str = "playtime.json";
+const PLAYTIME_TEMPORARY_PATH: &str = "playtime.json.tmp";
+
+// Daily playtime kept, enough for weekly leaderboards
+const DAYS_KEPT: i64 = 60;
+
+// Players shown in the weekly leaderboard
+const LEADERBOARD_SIZE: usize = 10;
+
+// Add the session's playtime to every player's totals
+pub fn record(context: &Context, report: &SessionReport) {
+    let mut data = match load(context) {
+        Ok(data) => data,
+        Err(error) => return println!("[WARN] {}", error),
+    };
+    let players = &mut data["players"];
+    for (player, from, to) in &report.play_intervals {
+        let entry = &mut players[player];
+        for (day, seconds) in split_by_day(*from, *to) {
+            let day = day.format("%Y-%m-%d").to_string();
+            entry["days"][&day] = json!(entry["days"][&day].as_i64().unwrap_or(0) + seconds);
+            entry["total_seconds"] = json!(entry["total_seconds"].as_i64().unwrap_or(0) + seconds);
+        }
+        let last_seen = entry["last_seen"].as_str().and_then(parse_time);
+        if last_seen.is_none_or(|last_seen| last_seen < *to) {
+            entry["last_seen"] = json!(to.to_rfc3339());
+        }
+    }
+    let session_players = report
+        .play_intervals
+        .iter()
+        .map(|(player, _, _)| player)
+        .collect::<HashSet<&String>>();
+    for player in session_players {
+        let entry = &mut players[player];
+        entry["sessions"] = json!(entry["sessions"].as_u64().unwrap_or(0) + 1);
+    }
+
+    // Forget old days
+    let oldest = (context.clock.now() - Duration::days(DAYS_KEPT))
+        .format("%Y-%m-%d")
+        .to_string();
+    if let Some(players) = data["players"].as_object_mut() {
+        for entry in players.values_mut() {
+            if let Some(days) = entry["days"].as_object_mut() {
+                let old = days
+                    .keys()
+                    .filter(|day| **day < oldest)
+                    .cloned()
+                    .collect::<Vec<String>>();
+                for day in old {
+                    days.remove(&day);
+                }
+            }
+        }
+    }
+    save(context, &data);
+}
+
+// Leaderboard for the past week, once a week when playtime-leaderboard=true
+pub fn weekly_leaderboard(context: &Context) -> Option<Vec<(String, Duration)>> {
+    let contents = fs::read_to_string(context.path("server.properties")).unwrap_or_default();
+    if properties::get(&contents, "playtime-leaderboard") != Some("true") {
+        return None;
+    }
+    let mut data = match load(context) {
+        Ok(data) => data,
+        Err(error) => {
+            println!("[WARN] {}", error);
+            return None;
+        }
+    };
+    let now = context.clock.now();
+    let last_leaderboard = data["last_leaderboard"].as_str().and_then(parse_time);
+    if last_leaderboard.is_some_and(|last_leaderboard| now - last_leaderboard < Duration::weeks(1))
+    {
+        return None;
+    }
+    data["last_leaderboard"] = json!(now.to_rfc3339());
+    save(context, &data);
+
+    let mut leaderboard = totals(&data, Some(now.naive_local().date() - Duration::days(6)));
+    leaderboard.truncate(LEADERBOARD_SIZE);
+    Some(leaderboard)
+}
+
+// `playtime [--week]`, print the playtime leaderboard
+pub fn run(context: &Context, args: &[String]) -> Result<(), LauncherError> {
+    let week = match args {
+        [] => false,
+        [arg] if arg == "--week" => true,
+        _ => return Err(LauncherError::Usage("playtime [--week]".to_owned())),
+    };
+    let data = load(context).map_err(LauncherError::Data)?;
+    let since = if week {
+        Some(context.clock.now().naive_local().date() - Duration::days(6))
+    } else {
+        None
+    };
+    let leaderboard = totals(&data, since);
+    if leaderboard.is_empty() {
+        println!("[INFO] No playtime recorded yet");
+        return Ok(());
+    }
+
+    println!(
+        "{:<5} {:<16} {:>14} {:>9}  Last seen",
+        "Rank", "Player", "Playtime", "Sessions"
+    );
+    for (rank, (player, time)) in leaderboard.iter().enumerate() {
+        let entry = &data["players"][player];
+        println!(
+            "{:<5} {:<16} {:>14} {:>9}  {}",
+            format!("{}.", rank + 1),
+            player,
+            control::format_duration(*time),
+            entry["sessions"].as_u64().unwrap_or(0),
+            entry["last_seen"]
+                .as_str()
+                .and_then(parse_time)
+                .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_default()
+        );
+    }
+    Ok(())
+}
+
+// Playtime per player, all time or from a day on, longest first
+fn totals(data: &Value, since: Option<NaiveDate>) -> Vec<(String, Duration)> {
+    let players = match data["players"].as_object() {
+        Some(players) => players,
+        None => return Vec::new(),
+    };
+    let mut totals = players
+        .iter()
+        .map(|(player, entry)| {
+            let seconds = match since {
+                Some(since) => entry["days"]
+                    .as_object()
+                    .map(|days| {
+                        days.iter()
+                            .filter(|(day, _)| {
+                                NaiveDate::parse_from_str(day, "%Y-%m-%d")
+                                    .is_ok_and(|day| day >= since)
+                            })
+                            .filter_map(|(_, seconds)| seconds.as_i64())
+                            .sum()
+                    })
+                    .unwrap_or(0),
+                None => entry["total_seconds"].as_i64().unwrap_or(0),
+            };
+            (player.clone(), Duration::seconds(seconds))
+        })
+        .filter(|(_, time)| *time > Duration::zero())
+        .collect::<Vec<(String, Duration)>>();
+    totals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    totals
+}
+
+// Seconds online per day, for a stay that may cross midnight
+fn split_by_day(from: DateTime<Local>, to: DateTime<Local>) -> Vec<(NaiveDate, i64)> {
+    let mut days = Vec::new();
+    let mut start = from;
+    while start < to {
+        let day = start.naive_local().date();
+        let midnight = Local
+            .from_local_datetime(&day.succ().and_hms(0, 0, 0))
+            .earliest()
+            .unwrap_or(to);
+        let end = midnight.min(to);
+        days.push((day, (end - start).num_seconds()));
+        start = end;
+    }
+    days
+}
+
+fn parse_time(time: &str) -> Option<DateTime<Local>> {
+    DateTime::parse_from_rfc3339(time)
+        .ok()
+        .map(|time| time.with_timezone(&Local))
+}
+
+// Saved playtime, none if the file can't be used so it isn't overwritten
+fn load(context: &Context) -> Result<Value, String> {
+    match fs::read_to_string(context.path(PLAYTIME_PATH)) {
+        Ok(contents) => match serde_json::from_str(&contents) {
+            Ok(Value::Object(data)) => {
+                let data = Value::Object(data);
+                check_shape(&data)?;
+                Ok(data)
+            }
+            Ok(_) => Err(format!("{} is not a JSON object", PLAYTIME_PATH)),
+            Err(error) => Err(format!("Failed to parse {}: {}", PLAYTIME_PATH, error)),
+        },
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(json!({ "players": {} })),
+        Err(error) => Err(format!("Failed to read {}: {}", PLAYTIME_PATH, error)),
+    }
+}
+
+// Players and their days must be objects, record() fills them in place
+fn check_shape(data: &Value) -> Result<(), String> {
+    let players = match &data["players"] {
+        Value::Null => return Ok(()),
+        Value::Object(players) => players,
+        _ => return Err(format!("players in {} is not a JSON object", PLAYTIME_PATH)),
+    };
+    for (player, entry) in players {
+        let valid = match entry {
+            Value::Object(entry) => entry
+                .get("days")
+                .is_none_or(|days| days.is_object() || days.is_null()),
+            _ => false,
+        };
+        if !valid {
+            return Err(format!(
+                "Playtime of {} in {} is not a JSON object with days",
+                player, PLAYTIME_PATH
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Write through a temporary file so a crash never leaves half a file
+fn save(context: &Context, data: &Value) {
+    let temporary_path = context.path(PLAYTIME_TEMPORARY_PATH);
+    let result = fs::write(&temporary_path, data.to_string())
+        .and_then(|_| fs::rename(&temporary_path, context.path(PLAYTIME_PATH)));
+    if let Err(error) = result {
+        println!("[WARN] Failed to save {}: {}", PLAYTIME_PATH, error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    #[test]
+    fn refuses_malformed_playtime() {
+        let root = env::temp_dir().join(format!("launcher-playtime-{}", process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let context = Context {
+            root: root.clone(),
+            ..Context::default()
+        };
+        let load_contents = |contents: &str| {
+            fs::write(root.join(PLAYTIME_PATH), contents).unwrap();
+            load(&context)
+        };
+
+        assert!(load_contents(
+            r#"{"players": {"Steve": {"total_seconds": 60, "days": {"2021-05-01": 60}}}}"#
+        )
+        .is_ok());
+        assert!(load_contents(r#"{"last_leaderboard": "2021-05-01T12:00:00+00:00"}"#).is_ok());
+        assert!(load_contents("[]").is_err());
+        assert!(load_contents(r#"{"players": []}"#).is_err());
+        assert!(load_contents(r#"{"players": {"Steve": 60}}"#).is_err());
+        assert!(load_contents(r#"{"players": {"Steve": {"days": [60]}}}"#).is_err());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn splits_stays_at_midnight() {
+        let from = Local.ymd(2021, 5, 1).and_hms(23, 30, 0);
+        let to = Local.ymd(2021, 5, 2).and_hms(0, 45, 0);
+
+        assert_eq!(
+            split_by_day(from, to),
+            vec![
+                (NaiveDate::from_ymd(2021, 5, 1), 30 * 60),
+                (NaiveDate::from_ymd(2021, 5, 2), 45 * 60)
+            ]
+        );
+    }
+}
@@ -0,0 +1,270 @@
+// Checks run before launching, so problems show up before anyone is told the server is starting
+use crate::context::Context;
+use crate::properties;
+use crate::upgrade;
+use regex::Regex;
+use std::cmp::Ordering;
+use std::fs;
+use std::io::{self, stdout, ErrorKind, IsTerminal, Write};
+use std::net::TcpListener;
+use std::path::Path;
+use std::process::Command;
+
+const EULA_PATH: &str = "eula.txt";
+const EULA_URL: &str = "https://aka.ms/MinecraftEULA";
+
+enum Outcome {
+    Pass,
+    Warn,
+    Fail,
+}
+
+// Check everything needed to launch, printing a report
+// Returns false when the server shouldn't be launched
+pub fn check(context: &Context, contents: &str) -> bool {
+    let mut report = Vec::new();
+    let mut add =
+        |outcome, name: &str, detail: String| report.push((outcome, name.to_owned(), detail));
+
+    let level_name = properties::get(contents, "level-name").filter(|name| !name.is_empty());
+    let server_version =
+        properties::get(contents, "server-version").filter(|version| !version.is_empty());
+    match (level_name, server_version) {
+        (Some(level_name), Some(server_version)) => add(
+            Outcome::Pass,
+            "server.properties",
+            format!("level '{}', Minecraft {}", level_name, server_version),
+        ),
+        _ => add(
+            Outcome::Fail,
+            "server.properties",
+            "level-name and server-version must both be set".to_owned(),
+        ),
+    }
+
+    if let Some(server_version) = server_version {
+        let jar_path = context.path(format!("jars/{}.jar", server_version));
+        if jar_path.is_file() {
+            add(Outcome::Pass, "Server jar", jar_path.display().to_string());
+        } else {
+            add(
+                Outcome::Fail,
+                "Server jar",
+                format!("{} not found", jar_path.display()),
+            );
+        }
+    }
+
+    if eula_accepted(context) {
+        add(Outcome::Pass, "EULA", "accepted".to_owned());
+    } else {
+        add(
+            Outcome::Fail,
+            "EULA",
+            format!(
+                "not accepted, set eula=true in {} ({})",
+                context.path(EULA_PATH).display(),
+                EULA_URL
+            ),
+        );
+    }
+
+    let (outcome, detail) = java(context, server_version);
+    add(outcome, "Java", detail);
+
+    // Nothing should be listening where the server is about to
+    let host = match properties::get(contents, "server-ip") {
+        Some(host) if !host.is_empty() => host,
+        _ => "0.0.0.0",
+    };
+    let port = properties::get_number(contents, "server-port", 25565);
+    match TcpListener::bind((host, port)) {
+        Ok(_) => add(Outcome::Pass, "Port", format!("{}:{} is free", host, port)),
+        Err(error) if error.kind() == ErrorKind::AddrInUse => add(
+            Outcome::Fail,
+            "Port",
+            format!("{}:{} is already in use", host, port),
+        ),
+        Err(error) => add(
+            Outcome::Warn,
+            "Port",
+            format!("couldn't check {}:{}: {}", host, port, error),
+        ),
+    }
+
+    let minimum = properties::get_number(contents, "preflight-min-free-mb", 1024u64);
+    match free_megabytes(context) {
+        Some(free) if free < minimum => add(
+            Outcome::Fail,
+            "Disk space",
+            format!("{} MB free, at least {} MB needed", free, minimum),
+        ),
+        Some(free) => add(Outcome::Pass, "Disk space", format!("{} MB free", free)),
+        None => add(Outcome::Warn, "Disk space", "couldn't run df".to_owned()),
+    }
+
+    // Lock file, backups, jars and the world itself are written to
+    let directories = ["", level_name.unwrap_or_default(), "backups", "jars"];
+    let unwritable = directories
+        .iter()
+        .map(|directory| context.path(directory))
+        .filter(|directory| directory.is_dir())
+        .filter_map(|directory| writable(&directory).err())
+        .collect::<Vec<String>>();
+    if unwritable.is_empty() {
+        add(
+            Outcome::Pass,
+            "Permissions",
+            "directories are writable".to_owned(),
+        );
+    } else {
+        add(Outcome::Fail, "Permissions", unwritable.join(", "));
+    }
+
+    println!("[INFO] Pre-flight checks:");
+    let mut passed = true;
+    for (outcome, name, detail) in &report {
+        let label = match outcome {
+            Outcome::Pass => " OK ",
+            Outcome::Warn => "WARN",
+            Outcome::Fail => {
+                passed = false;
+                "FAIL"
+            }
+        };
+        println!("  [{}] {}: {}", label, name, detail);
+    }
+    passed
+}
+
+// Ask to accept the EULA when it hasn't been, only with someone at the console
+pub fn offer_eula(context: &Context) {
+    if eula_accepted(context) || !io::stdin().is_terminal() {
+        return;
+    }
+    println!("\nThe Minecraft EULA has not been accepted ({})", EULA_URL);
+    print!("Accept it? [y/N] > ");
+    let _ = stdout().flush();
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err()
+        || !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+    {
+        return;
+    }
+
+    let path = context.path(EULA_PATH);
+    let contents = fs::read_to_string(&path).unwrap_or_else(|_| {
+        format!(
+            "#By changing the setting below to TRUE you are indicating your agreement to our EULA ({}).\n#{}\n",
+            EULA_URL,
+            context.clock.now().format("%a %b %d %H:%M:%S %Z %Y")
+        )
+    });
+    match fs::write(&path, properties::set(&contents, "eula", "true")) {
+        Ok(()) => println!("[INFO] EULA accepted in {}", path.display()),
+        Err(error) => println!("[ERROR] Failed to write {}: {}", path.display(), error),
+    }
+}
+
+fn eula_accepted(context: &Context) -> bool {
+    fs::read_to_string(context.path(EULA_PATH))
+        .is_ok_and(|contents| properties::get(&contents, "eula") == Some("true"))
+}
+
+// Installed Java against what the Minecraft version needs
+fn java(context: &Context, server_version: Option<&str>) -> (Outcome, String) {
+    // java -version prints to stderr
+    let output = match context.java().arg("-version").output() {
+        Ok(output) => String::from_utf8_lossy(&output.stderr).into_owned(),
+        Err(error) => return (Outcome::Fail, format!("couldn't run java: {}", error)),
+    };
+    let installed = match java_major_version(&output) {
+        Some(installed) => installed,
+        None => {
+            return (
+                Outcome::Warn,
+                "couldn't tell the installed version".to_owned(),
+            )
+        }
+    };
+    match server_version.and_then(required_java) {
+        Some(required) if installed < required => (
+            Outcome::Fail,
+            format!(
+                "Java {} installed, Minecraft {} needs Java {}",
+                installed,
+                server_version.unwrap_or_default(),
+                required
+            ),
+        ),
+        _ => (Outcome::Pass, format!("Java {}", installed)),
+    }
+}
+
+// Major version from `java -version`, such as 8 for "1.8.0_292" and 17 for "17.0.2"
+fn java_major_version(output: &str) -> Option<u32> {
+    let regex = Regex::new(r#"version "(\d+)(?:\.(\d+))?"#).unwrap();
+    let captures = regex.captures(output)?;
+    let major = captures.get(1)?.as_str().parse().ok()?;
+    if major == 1 {
+        captures.get(2)?.as_str().parse().ok()
+    } else {
+        Some(major)
+    }
+}
+
+// Oldest Java a release runs on, none for snapshots and the like
+fn required_java(server_version: &str) -> Option<u32> {
+    let at_least = |version| {
+        upgrade::compare_versions(server_version, version).map(|order| order != Ordering::Less)
+    };
+    Some(if at_least("1.20.5")? {
+        21
+    } else if at_least("1.18")? {
+        17
+    } else if at_least("1.17")? {
+        16
+    } else {
+        8
+    })
+}
+
+// Free space where the server runs, from df
+fn free_megabytes(context: &Context) -> Option<u64> {
+    let output = Command::new("df")
+        .arg("-Pk")
+        .arg(&context.root)
+        .output()
+        .ok()?;
+    let output = String::from_utf8_lossy(&output.stdout);
+    let available = output.lines().nth(1)?.split_whitespace().nth(3)?;
+    available
+        .parse::<u64>()
+        .ok()
+        .map(|kilobytes| kilobytes / 1024)
+}
+
+fn writable(directory: &Path) -> Result<(), String> {
+    let path = directory.join(".preflight");
+    fs::write(&path, b"")
+        .and_then(|()| fs::remove_file(&path))
+        .map_err(|error| format!("{} is not writable ({})", directory.display(), error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_java_versions() {
+        let java_8 = "openjdk version \"1.8.0_292\"\nOpenJDK Runtime Environment";
+        let java_17 = "openjdk version \"17.0.2\" 2022-01-18\nOpenJDK Runtime Environment";
+
+        assert_eq!(java_major_version(java_8), Some(8));
+        assert_eq!(java_major_version(java_17), Some(17));
+        assert_eq!(required_java("1.16.5"), Some(8));
+        assert_eq!(required_java("1.18.2"), Some(17));
+        assert_eq!(required_java("1.20.6"), Some(21));
+        assert_eq!(required_java("21w44a"), None);
+    }
+}
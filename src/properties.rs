@@ -0,0 +1,61 @@
+use crate::control;
+use chrono::Duration;
+
+// Look up a key in server.properties contents
+pub fn get<'a>(contents: &'a str, key: &str) -> Option<&'a str> {
+    contents.lines().find_map(|line| {
+        let (line_key, value) = line.split_once('=')?;
+        if line_key.trim() == key {
+            Some(value.trim())
+        } else {
+            None
+        }
+    })
+}
+
+// Set a key in server.properties contents, adding it if missing
+pub fn set(contents: &str, key: &str, value: &str) -> String {
+    let mut found = false;
+    let mut lines = contents
+        .lines()
+        .map(|line| match line.split_once('=') {
+            Some((line_key, _)) if line_key.trim() == key && !found => {
+                found = true;
+                format!("{}={}", key, value)
+            }
+            _ => line.to_owned(),
+        })
+        .collect::<Vec<String>>();
+    if !found {
+        lines.push(format!("{}={}", key, value));
+    }
+    lines.join("\n") + "\n"
+}
+
+// Look up a duration such as "30m", falling back to a default
+pub fn get_duration(contents: &str, key: &str, default: Duration) -> Duration {
+    match get(contents, key) {
+        Some(value) => match control::parse_duration(value) {
+            Some(duration) => duration,
+            None => {
+                println!("[WARN] Invalid duration for {}: '{}'", key, value);
+                default
+            }
+        },
+        None => default,
+    }
+}
+
+// Look up a number, falling back to a default
+pub fn get_number<T: std::str::FromStr>(contents: &str, key: &str, default: T) -> T {
+    match get(contents, key) {
+        Some(value) => match value.parse::<T>() {
+            Ok(number) => number,
+            Err(_) => {
+                println!("[WARN] Invalid number for {}: '{}'", key, value);
+                default
+            }
+        },
+        None => default,
+    }
+}
@@ -0,0 +1,287 @@
+// Query protocol (GameSpy4), served over UDP when enable-query=true
+use crate::ping::ServerStatus;
+use crate::properties;
+use std::collections::HashMap;
+use std::io::{self, ErrorKind};
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+const MAGIC: [u8; 2] = [0xfe, 0xfd];
+const HANDSHAKE: u8 = 0x09;
+const STAT: u8 = 0x00;
+
+// Padding around the key/value section and the player list of a full stat
+const KEY_VALUE_PADDING: &[u8] = b"splitnum\x00\x80\x00";
+const PLAYER_PADDING: &[u8] = b"\x01player_\x00\x00";
+
+// Basic stat, what the server list shows
+pub struct BasicStat {
+    pub motd: String,
+    pub game_type: String,
+    pub map: String,
+    pub online_players: u32,
+    pub max_players: u32,
+    pub host_port: u16,
+    pub host_ip: String,
+}
+
+// Full stat, every server value and the player list
+pub struct FullStat {
+    pub values: HashMap<String, String>,
+    pub players: Vec<String>,
+}
+
+// Query port, when the server has query enabled
+pub fn port(contents: &str) -> Option<u16> {
+    if properties::get(contents, "enable-query") != Some("true") {
+        return None;
+    }
+    Some(properties::get_number(contents, "query.port", 25565))
+}
+
+// Full stat in the shape Server List Ping reports
+pub fn status(host: &str, port: u16, timeout: Duration) -> io::Result<ServerStatus> {
+    let sent = Instant::now();
+    let stat = full_stat(host, port, timeout)?;
+    let latency = sent.elapsed();
+    let value = |key: &str| stat.values.get(key).cloned().unwrap_or_default();
+    let number = |key: &str| value(key).parse::<u32>().unwrap_or(0);
+    Ok(ServerStatus {
+        version: value("version"),
+        protocol: -1,
+        motd: value("hostname"),
+        online_players: number("numplayers"),
+        max_players: number("maxplayers"),
+        sample: stat.players.clone(),
+        players: Some(stat.players),
+        latency,
+    })
+}
+
+pub fn basic_stat(host: &str, port: u16, timeout: Duration) -> io::Result<BasicStat> {
+    let (socket, session_id, challenge) = handshake(host, port, timeout)?;
+    let mut request = header(STAT, session_id);
+    request.extend_from_slice(&challenge.to_be_bytes());
+    let response = exchange(&socket, &request, STAT, session_id)?;
+
+    let mut reader = Reader::new(&response);
+    let motd = reader.string()?;
+    let game_type = reader.string()?;
+    let map = reader.string()?;
+    let online_players = parse_number(&reader.string()?)?;
+    let max_players = parse_number(&reader.string()?)?;
+    let host_port = reader.u16_le()?;
+    let host_ip = reader.string()?;
+    Ok(BasicStat {
+        motd,
+        game_type,
+        map,
+        online_players,
+        max_players,
+        host_port,
+        host_ip,
+    })
+}
+
+pub fn full_stat(host: &str, port: u16, timeout: Duration) -> io::Result<FullStat> {
+    let (socket, session_id, challenge) = handshake(host, port, timeout)?;
+    let mut request = header(STAT, session_id);
+    request.extend_from_slice(&challenge.to_be_bytes());
+    // Padding asks for the full stat instead of the basic one
+    request.extend_from_slice(&[0; 4]);
+    let response = exchange(&socket, &request, STAT, session_id)?;
+
+    let mut reader = Reader::new(&response);
+    reader.expect(KEY_VALUE_PADDING)?;
+    let mut values = HashMap::new();
+    loop {
+        let key = reader.string()?;
+        if key.is_empty() {
+            break;
+        }
+        values.insert(key, reader.string()?);
+    }
+    reader.expect(PLAYER_PADDING)?;
+    let mut players = Vec::new();
+    loop {
+        let player = reader.string()?;
+        if player.is_empty() {
+            break;
+        }
+        players.push(player);
+    }
+    Ok(FullStat { values, players })
+}
+
+// Get a challenge token, stats are only answered with a valid one
+fn handshake(host: &str, port: u16, timeout: Duration) -> io::Result<(UdpSocket, i32, i32)> {
+    let address = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| invalid(format!("no address for {}", host)))?;
+    let socket = UdpSocket::bind(if address.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    })?;
+    socket.connect(address)?;
+    socket.set_read_timeout(Some(timeout))?;
+    socket.set_write_timeout(Some(timeout))?;
+
+    // Servers only look at the low 4 bits of each byte
+    let session_id = (std::process::id() as i32) & 0x0f0f_0f0f;
+    let response = exchange(
+        &socket,
+        &header(HANDSHAKE, session_id),
+        HANDSHAKE,
+        session_id,
+    )?;
+    let token = Reader::new(&response).string()?;
+    let challenge = token
+        .parse::<i32>()
+        .map_err(|_| invalid(format!("invalid challenge token '{}'", token)))?;
+    Ok((socket, session_id, challenge))
+}
+
+fn header(kind: u8, session_id: i32) -> Vec<u8> {
+    let mut packet = MAGIC.to_vec();
+    packet.push(kind);
+    packet.extend_from_slice(&session_id.to_be_bytes());
+    packet
+}
+
+// Send a request and return the response payload after its type and session ID
+fn exchange(socket: &UdpSocket, request: &[u8], kind: u8, session_id: i32) -> io::Result<Vec<u8>> {
+    socket.send(request)?;
+    let mut buffer = vec![0; 65536];
+    let length = socket.recv(&mut buffer)?;
+    let response = &buffer[..length];
+    if length < 5 || response[0] != kind || response[1..5] != session_id.to_be_bytes() {
+        return Err(invalid("unexpected response".to_owned()));
+    }
+    Ok(response[5..].to_vec())
+}
+
+fn parse_number(text: &str) -> io::Result<u32> {
+    text.parse::<u32>()
+        .map_err(|_| invalid(format!("invalid number '{}'", text)))
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+// Reads the null-terminated strings responses are made of
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Reader<'a> {
+        Reader { bytes }
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let end = self
+            .bytes
+            .iter()
+            .position(|&byte| byte == 0)
+            .ok_or_else(|| invalid("unterminated string".to_owned()))?;
+        // Values are Latin-1
+        let text = self.bytes[..end].iter().map(|&byte| byte as char).collect();
+        self.bytes = &self.bytes[end + 1..];
+        Ok(text)
+    }
+
+    fn u16_le(&mut self) -> io::Result<u16> {
+        if self.bytes.len() < 2 {
+            return Err(invalid("truncated response".to_owned()));
+        }
+        let value = u16::from_le_bytes([self.bytes[0], self.bytes[1]]);
+        self.bytes = &self.bytes[2..];
+        Ok(value)
+    }
+
+    fn expect(&mut self, padding: &[u8]) -> io::Result<()> {
+        if !self.bytes.starts_with(padding) {
+            return Err(invalid("unexpected padding".to_owned()));
+        }
+        self.bytes = &self.bytes[padding.len()..];
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    // Answer a handshake and one stat request like a server would
+    fn fake_server() -> u16 {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = socket.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let mut buffer = [0; 1024];
+            let (length, client) = socket.recv_from(&mut buffer).unwrap();
+            assert_eq!(&buffer[..3], &[0xfe, 0xfd, HANDSHAKE]);
+            assert_eq!(length, 7);
+            let session = buffer[3..7].to_vec();
+            let mut response = vec![HANDSHAKE];
+            response.extend(&session);
+            response.extend(b"9513307\x00");
+            socket.send_to(&response, client).unwrap();
+
+            let (length, client) = socket.recv_from(&mut buffer).unwrap();
+            assert_eq!(&buffer[..3], &[0xfe, 0xfd, STAT]);
+            assert_eq!(&buffer[7..11], &9513307i32.to_be_bytes());
+            let mut response = vec![STAT];
+            response.extend(&session);
+            if length == 11 {
+                response.extend(b"A Minecraft Server\x00SMP\x00world\x002\x0020\x00");
+                response.extend(&25565u16.to_le_bytes());
+                response.extend(b"127.0.0.1\x00");
+            } else {
+                response.extend(KEY_VALUE_PADDING);
+                response.extend(
+                    b"hostname\x00A Minecraft Server\x00gametype\x00SMP\x00version\x001.16.5\x00",
+                );
+                response.extend(b"numplayers\x002\x00maxplayers\x0020\x00\x00");
+                response.extend(PLAYER_PADDING);
+                response.extend(b"Steve\x00Alex\x00\x00");
+            }
+            socket.send_to(&response, client).unwrap();
+        });
+        port
+    }
+
+    #[test]
+    fn reads_basic_stat() {
+        let port = fake_server();
+        let stat = basic_stat("127.0.0.1", port, Duration::from_secs(5)).unwrap();
+
+        assert_eq!(stat.motd, "A Minecraft Server");
+        assert_eq!(
+            (stat.game_type.as_str(), stat.map.as_str()),
+            ("SMP", "world")
+        );
+        assert_eq!((stat.online_players, stat.max_players), (2, 20));
+        assert_eq!(
+            (stat.host_port, stat.host_ip.as_str()),
+            (25565, "127.0.0.1")
+        );
+    }
+
+    #[test]
+    fn reads_full_stat_with_players() {
+        let port = fake_server();
+        let status = status("127.0.0.1", port, Duration::from_secs(5)).unwrap();
+
+        assert_eq!(status.version, "1.16.5");
+        assert_eq!(status.motd, "A Minecraft Server");
+        assert_eq!((status.online_players, status.max_players), (2, 20));
+        assert_eq!(
+            status.players,
+            Some(vec!["Steve".to_owned(), "Alex".to_owned()])
+        );
+    }
+}
@@ -0,0 +1,174 @@
+// RCON, the remote console served over TCP when enable-rcon=true
+use crate::properties;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+const LOGIN: i32 = 3;
+const COMMAND: i32 = 2;
+const RESPONSE: i32 = 0;
+
+// Largest packet servers send, responses above it are split
+const MAX_PACKET_LENGTH: usize = 4110;
+
+// Port and password, when the server has RCON enabled with a password
+pub fn config(contents: &str) -> Option<(u16, String)> {
+    if properties::get(contents, "enable-rcon") != Some("true") {
+        return None;
+    }
+    let password =
+        properties::get(contents, "rcon.password").filter(|password| !password.is_empty())?;
+    Some((
+        properties::get_number(contents, "rcon.port", 25575),
+        password.to_owned(),
+    ))
+}
+
+// Logged in connection to the remote console
+pub struct Rcon {
+    stream: TcpStream,
+    next_id: i32,
+}
+
+impl Rcon {
+    pub fn connect(host: &str, port: u16, password: &str, timeout: Duration) -> io::Result<Rcon> {
+        let address = (host, port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| invalid(format!("no address for {}", host)))?;
+        let stream = TcpStream::connect_timeout(&address, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+
+        let mut rcon = Rcon { stream, next_id: 1 };
+        let id = rcon.send(LOGIN, password)?;
+        // Failed logins are answered with ID -1
+        let (response_id, _, _) = rcon.receive()?;
+        if response_id != id {
+            return Err(io::Error::new(
+                ErrorKind::PermissionDenied,
+                "wrong RCON password",
+            ));
+        }
+        Ok(rcon)
+    }
+
+    // Run a console command, returning what it printed
+    pub fn command(&mut self, command: &str) -> io::Result<String> {
+        let id = self.send(COMMAND, command)?;
+        let (response_id, kind, payload) = self.receive()?;
+        if response_id != id || kind != RESPONSE {
+            return Err(invalid("unexpected response".to_owned()));
+        }
+        Ok(payload)
+    }
+
+    fn send(&mut self, kind: i32, payload: &str) -> io::Result<i32> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let mut packet = Vec::new();
+        packet.extend_from_slice(&((payload.len() + 10) as i32).to_le_bytes());
+        packet.extend_from_slice(&id.to_le_bytes());
+        packet.extend_from_slice(&kind.to_le_bytes());
+        packet.extend_from_slice(payload.as_bytes());
+        packet.extend_from_slice(&[0, 0]);
+        self.stream.write_all(&packet)?;
+        Ok(id)
+    }
+
+    fn receive(&mut self) -> io::Result<(i32, i32, String)> {
+        let mut length = [0; 4];
+        self.stream.read_exact(&mut length)?;
+        let length = i32::from_le_bytes(length);
+        if length < 10 || length as usize > MAX_PACKET_LENGTH {
+            return Err(invalid(format!("invalid packet length {}", length)));
+        }
+        let mut body = vec![0; length as usize];
+        self.stream.read_exact(&mut body)?;
+        let id = i32::from_le_bytes([body[0], body[1], body[2], body[3]]);
+        let kind = i32::from_le_bytes([body[4], body[5], body[6], body[7]]);
+        let payload = String::from_utf8_lossy(&body[8..body.len() - 2]).into_owned();
+        Ok((id, kind, payload))
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn read_packet(stream: &mut TcpStream) -> (i32, i32, String) {
+        let mut length = [0; 4];
+        stream.read_exact(&mut length).unwrap();
+        let mut body = vec![0; i32::from_le_bytes(length) as usize];
+        stream.read_exact(&mut body).unwrap();
+        let id = i32::from_le_bytes([body[0], body[1], body[2], body[3]]);
+        let kind = i32::from_le_bytes([body[4], body[5], body[6], body[7]]);
+        (
+            id,
+            kind,
+            String::from_utf8(body[8..body.len() - 2].to_vec()).unwrap(),
+        )
+    }
+
+    fn write_packet(stream: &mut TcpStream, id: i32, kind: i32, payload: &str) {
+        let mut packet = ((payload.len() + 10) as i32).to_le_bytes().to_vec();
+        packet.extend_from_slice(&id.to_le_bytes());
+        packet.extend_from_slice(&kind.to_le_bytes());
+        packet.extend_from_slice(payload.as_bytes());
+        packet.extend_from_slice(&[0, 0]);
+        stream.write_all(&packet).unwrap();
+    }
+
+    // Accept one login, then answer one command like a server would
+    fn fake_server(password: &'static str) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let (id, kind, payload) = read_packet(&mut stream);
+            assert_eq!(kind, LOGIN);
+            if payload != password {
+                write_packet(&mut stream, -1, COMMAND, "");
+                return;
+            }
+            write_packet(&mut stream, id, COMMAND, "");
+
+            let (id, kind, payload) = read_packet(&mut stream);
+            assert_eq!((kind, payload.as_str()), (COMMAND, "list"));
+            write_packet(
+                &mut stream,
+                id,
+                RESPONSE,
+                "There are 1 of a max of 20 players online: Steve",
+            );
+        });
+        port
+    }
+
+    #[test]
+    fn runs_command_after_login() {
+        let port = fake_server("secret");
+        let mut rcon = Rcon::connect("127.0.0.1", port, "secret", Duration::from_secs(5)).unwrap();
+
+        assert_eq!(
+            rcon.command("list").unwrap(),
+            "There are 1 of a max of 20 players online: Steve"
+        );
+    }
+
+    #[test]
+    fn rejects_wrong_password() {
+        let port = fake_server("secret");
+        let error = Rcon::connect("127.0.0.1", port, "guess", Duration::from_secs(5))
+            .err()
+            .unwrap();
+
+        assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::messages;
+use chrono::prelude::{DateTime, Local};
+use chrono::Duration;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+
+// Why a session ended
+#[derive(Clone, Copy, PartialEq)]
+pub enum ShutdownReason {
+    // Scheduled shutdown time was reached
+    Scheduled,
+    // Server exited with an error
+    Crash,
+    // Server was stopped by an operator
+    Manual,
+    // Launcher received SIGINT or SIGTERM
+    Signal,
+}
+
+impl ShutdownReason {
+    const ALL: [ShutdownReason; 4] = [
+        ShutdownReason::Scheduled,
+        ShutdownReason::Crash,
+        ShutdownReason::Manual,
+        ShutdownReason::Signal,
+    ];
+
+    // Name kept in the session history, independent of the locale
+    pub fn name(self) -> &'static str {
+        match self {
+            ShutdownReason::Scheduled => "scheduled",
+            ShutdownReason::Crash => "crash",
+            ShutdownReason::Manual => "manual",
+            ShutdownReason::Signal => "signal",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<ShutdownReason> {
+        ShutdownReason::ALL
+            .iter()
+            .copied()
+            .find(|reason| reason.name() == name)
+    }
+
+    // Name in the configured locale
+    pub fn label(self) -> String {
+        messages::get(match self {
+            ShutdownReason::Scheduled => "reason.scheduled",
+            ShutdownReason::Crash => "reason.crash",
+            ShutdownReason::Manual => "reason.manual",
+            ShutdownReason::Signal => "reason.signal",
+        })
+    }
+
+    // Discord embed colour for the outcome
+    pub fn color(self) -> u32 {
+        match self {
+            ShutdownReason::Scheduled => 3451439,
+            ShutdownReason::Crash => 15158332,
+            ShutdownReason::Manual => 3447003,
+            ShutdownReason::Signal => 15105570,
+        }
+    }
+}
+
+// Something that happened during a session, kept for the history
+pub enum SessionEvent {
+    Joined(String),
+    Left(String),
+    Backup(Result<PathBuf, String>),
+    Restarted(String),
+}
+
+// What happened during a session
+pub struct SessionReport {
+    pub level_name: String,
+    // World directory, in the server directory
+    pub world_path: PathBuf,
+    pub server_version: String,
+    pub start_time: DateTime<Local>,
+    pub end_time: DateTime<Local>,
+    // Shutdown time when the session ended, after any extensions
+    pub scheduled_time: Option<DateTime<Local>>,
+    pub reason: ShutdownReason,
+    pub exit_status: Option<ExitStatus>,
+    pub peak_players: usize,
+    pub joined_players: Vec<String>,
+    pub events: Vec<(DateTime<Local>, SessionEvent)>,
+    // Who was online from when until when
+    pub play_intervals: Vec<(String, DateTime<Local>, DateTime<Local>)>,
+}
+
+impl SessionReport {
+    pub fn exit_status(&self) -> String {
+        match self.exit_status {
+            Some(status) => format!("{}", status),
+            None => "unknown".to_owned(),
+        }
+    }
+
+    // Time each player spent online, in the order they first joined
+    pub fn playtimes(&self) -> Vec<(String, Duration)> {
+        self.joined_players
+            .iter()
+            .map(|player| {
+                let time = self
+                    .play_intervals
+                    .iter()
+                    .filter(|(name, _, _)| name == player)
+                    .fold(Duration::zero(), |total, (_, from, to)| {
+                        total + (*to - *from)
+                    });
+                (player.clone(), time)
+            })
+            .collect()
+    }
+
+    // Size of the world directory on disk
+    pub fn world_size(&self) -> String {
+        match directory_size(&self.world_path) {
+            Ok(bytes) => format_size(bytes),
+            Err(error) => {
+                println!("[WARN] Failed to measure world size: {}", error);
+                "unknown".to_owned()
+            }
+        }
+    }
+}
+
+// Total size of the files in a directory
+fn directory_size(path: &Path) -> io::Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            size += directory_size(&entry.path())?;
+        } else {
+            size += metadata.len();
+        }
+    }
+    Ok(size)
+}
+
+// Format a byte count as "12.3 MiB"
+fn format_size(bytes: u64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, units[unit])
+}
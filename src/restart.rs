@@ -0,0 +1,223 @@
+use crate::properties;
+use chrono::prelude::{DateTime, Local, TimeZone};
+use chrono::{Duration, NaiveTime};
+use std::process::Command;
+
+// When to restart a long-running server
+pub struct RestartPolicy {
+    // Restart every day at this time
+    time: Option<NaiveTime>,
+    // Restart after the server has been up this long
+    uptime: Option<Duration>,
+    // Restart as soon as nobody is online once the server has been up this long
+    empty_uptime: Option<Duration>,
+    // Restart once heap usage goes above this percentage
+    heap_threshold: Option<u32>,
+    // Restart when the watchdog finds the server hung, only with restart-on-hang=true
+    on_hang: bool,
+}
+
+impl RestartPolicy {
+    // Read restart-time, restart-uptime, restart-when-empty, restart-heap-threshold and restart-on-hang
+    // from server.properties
+    pub fn from_properties(contents: &str) -> RestartPolicy {
+        let time = properties::get(contents, "restart-time").and_then(|value| {
+            match NaiveTime::parse_from_str(value, "%H:%M") {
+                Ok(time) => Some(time),
+                Err(_) => {
+                    println!("[WARN] Invalid time for restart-time: '{}'", value);
+                    None
+                }
+            }
+        });
+        let uptime = |key| {
+            properties::get(contents, key)
+                .map(|_| properties::get_duration(contents, key, Duration::zero()))
+                .filter(|uptime| *uptime > Duration::zero())
+        };
+        let heap_threshold = properties::get(contents, "restart-heap-threshold")
+            .map(|_| properties::get_number(contents, "restart-heap-threshold", 0))
+            .filter(|threshold| *threshold > 0);
+        RestartPolicy {
+            time,
+            uptime: uptime("restart-uptime"),
+            empty_uptime: uptime("restart-when-empty"),
+            heap_threshold,
+            on_hang: properties::get(contents, "restart-on-hang") == Some("true"),
+        }
+    }
+
+    // Next planned restart for a server launched at the given time, with its reason
+    pub fn next_restart(&self, launch_time: DateTime<Local>) -> Option<(DateTime<Local>, String)> {
+        let mut restarts = Vec::new();
+        if let Some(time) = self.time {
+            // Skip restarts right after launching
+            let earliest = launch_time + Duration::hours(1);
+            let mut date = earliest.naive_local().date();
+            loop {
+                if let Some(restart) = Local.from_local_datetime(&date.and_time(time)).earliest() {
+                    if restart > earliest {
+                        restarts.push((restart, "Daily restart".to_owned()));
+                        break;
+                    }
+                }
+                date = date.succ();
+            }
+        }
+        if let Some(uptime) = self.uptime {
+            restarts.push((launch_time + uptime, "Uptime limit reached".to_owned()));
+        }
+        restarts.into_iter().min_by_key(|(restart, _)| *restart)
+    }
+
+    // Reason to restart now, when the server is empty and has been up long enough
+    pub fn empty_restart(
+        &self,
+        launch_time: DateTime<Local>,
+        now: DateTime<Local>,
+        online_players: usize,
+    ) -> Option<String> {
+        let uptime = self.empty_uptime?;
+        if online_players == 0 && now - launch_time >= uptime {
+            Some("Server empty".to_owned())
+        } else {
+            None
+        }
+    }
+
+    pub fn restarts_on_hang(&self) -> bool {
+        self.on_hang
+    }
+
+    pub fn checks_heap(&self) -> bool {
+        self.heap_threshold.is_some()
+    }
+
+    // Check heap usage of the server process against the threshold
+    pub fn heap_exceeded(&self, pid: u32) -> Option<String> {
+        let threshold = self.heap_threshold?;
+        let usage = heap_usage(pid)?;
+        if usage >= threshold {
+            Some(format!("Heap usage at {}%", usage))
+        } else {
+            None
+        }
+    }
+}
+
+// Heap usage percentage of a Java process, asked through jcmd
+fn heap_usage(pid: u32) -> Option<u32> {
+    let used = jcmd(pid, "GC.heap_info")?
+        .split("used ")
+        .nth(1)?
+        .split('K')
+        .next()?
+        .trim()
+        .parse::<u64>()
+        .ok()?
+        * 1024;
+    let max = jcmd(pid, "VM.flags")?
+        .split("-XX:MaxHeapSize=")
+        .nth(1)?
+        .split_whitespace()
+        .next()?
+        .parse::<u64>()
+        .ok()?;
+    if max == 0 {
+        return None;
+    }
+    Some((used * 100 / max) as u32)
+}
+
+// Run a jcmd diagnostic command against a Java process
+fn jcmd(pid: u32, command: &str) -> Option<String> {
+    match Command::new("jcmd")
+        .arg(pid.to_string())
+        .arg(command)
+        .output()
+    {
+        Ok(output) if output.status.success() => {
+            Some(String::from_utf8_lossy(&output.stdout).into_owned())
+        }
+        Ok(output) => {
+            println!("[WARN] jcmd {} failed ({})", command, output.status);
+            None
+        }
+        Err(error) => {
+            println!("[WARN] Unable to call `jcmd`: {}", error);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local.ymd(2021, 5, day).and_hms(hour, minute, 0)
+    }
+
+    #[test]
+    fn restarts_at_the_time_of_day() {
+        let policy = RestartPolicy::from_properties("restart-time=06:00\n");
+        let launch_time = at(1, 3, 30);
+        assert_eq!(
+            policy.next_restart(launch_time),
+            Some((at(1, 6, 0), "Daily restart".to_owned()))
+        );
+
+        // Not within the first hour, tomorrow instead
+        let policy = RestartPolicy::from_properties("restart-time=04:00\n");
+        assert_eq!(
+            policy.next_restart(launch_time).map(|(time, _)| time),
+            Some(at(2, 4, 0))
+        );
+
+        let policy = RestartPolicy::from_properties("restart-time=4am\n");
+        assert_eq!(policy.next_restart(launch_time), None);
+    }
+
+    #[test]
+    fn restarts_after_the_uptime_limit() {
+        let policy = RestartPolicy::from_properties("restart-uptime=12h\n");
+        let launch_time = at(1, 3, 30);
+        assert_eq!(
+            policy.next_restart(launch_time),
+            Some((at(1, 15, 30), "Uptime limit reached".to_owned()))
+        );
+
+        // Whichever comes first
+        let policy = RestartPolicy::from_properties("restart-uptime=12h\nrestart-time=06:00\n");
+        assert_eq!(
+            policy.next_restart(launch_time).map(|(time, _)| time),
+            Some(at(1, 6, 0))
+        );
+        let policy = RestartPolicy::from_properties("restart-uptime=2h\nrestart-time=06:00\n");
+        assert_eq!(
+            policy.next_restart(launch_time).map(|(time, _)| time),
+            Some(at(1, 5, 30))
+        );
+        assert_eq!(
+            RestartPolicy::from_properties("").next_restart(launch_time),
+            None
+        );
+    }
+
+    #[test]
+    fn restarts_an_empty_server() {
+        let policy = RestartPolicy::from_properties("restart-when-empty=6h\n");
+        let launch_time = at(1, 3, 30);
+        // Up too short, or someone is playing
+        assert_eq!(policy.empty_restart(launch_time, at(1, 9, 0), 0), None);
+        assert_eq!(policy.empty_restart(launch_time, at(1, 9, 30), 2), None);
+        assert_eq!(
+            policy.empty_restart(launch_time, at(1, 9, 30), 0),
+            Some("Server empty".to_owned())
+        );
+
+        // Never without restart-when-empty
+        let policy = RestartPolicy::from_properties("restart-uptime=6h\n");
+        assert_eq!(policy.empty_restart(launch_time, at(2, 9, 30), 0), None);
+    }
+}
@@ -0,0 +1,357 @@
+use crate::messages;
+use chrono::prelude::{DateTime, Datelike, Local, TimeZone};
+use chrono::{Duration, NaiveTime, Weekday};
+
+// Shutdown reminder said in-game before the scheduled time
+pub struct Reminder {
+    pub before: Duration,
+    pub message: String,
+    pub sent: bool,
+}
+
+// Shutdown reminders, tightest first
+pub struct Reminders {
+    reminders: Vec<Reminder>,
+}
+
+impl Reminders {
+    pub fn shutdown() -> Reminders {
+        Reminders::new("reminder.shutdown")
+    }
+
+    pub fn restart() -> Reminders {
+        Reminders::new("reminder.restart")
+    }
+
+    // Reminders with messages from the catalog, such as "reminder.shutdown.5m"
+    fn new(prefix: &str) -> Reminders {
+        let reminders = [
+            (Duration::minutes(1), "1m"),
+            (Duration::minutes(5), "5m"),
+            (Duration::minutes(15), "15m"),
+            (Duration::minutes(30), "30m"),
+            (Duration::hours(1), "1h"),
+        ];
+        Reminders {
+            reminders: reminders
+                .iter()
+                .map(|&(before, name)| Reminder {
+                    before,
+                    message: messages::get(&format!("{}.{}", prefix, name)),
+                    sent: false,
+                })
+                .collect(),
+        }
+    }
+
+    // Reminder to say now, if any
+    // Only the tightest reminder is said, the ones before it are skipped
+    pub fn due(&mut self, remaining: Duration) -> Option<String> {
+        let index = self
+            .reminders
+            .iter()
+            .position(|reminder| remaining < reminder.before)?;
+        if self.reminders[index].sent {
+            return None;
+        }
+        for reminder in &mut self.reminders[index..] {
+            reminder.sent = true;
+        }
+        Some(self.reminders[index].message.clone())
+    }
+
+    // Re-arm reminders that are in the future again after the shutdown was pushed back
+    pub fn rearm(&mut self, remaining: Duration) {
+        for reminder in &mut self.reminders {
+            if remaining >= reminder.before {
+                reminder.sent = false;
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Reminder> {
+        self.reminders.iter()
+    }
+}
+
+// Time window during which the server should run
+pub struct Window<Tz: TimeZone = Local> {
+    pub start: DateTime<Tz>,
+    pub end: DateTime<Tz>,
+}
+
+// Days and time range of a schedule entry, the range may wrap past midnight
+struct Entry {
+    days: [bool; 7],
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+// Recurring session schedule, such as "weekdays 18:00-23:30, weekends 10:00-02:00"
+pub struct Schedule {
+    entries: Vec<Entry>,
+}
+
+impl Schedule {
+    pub fn parse(definition: &str) -> Result<Schedule, String> {
+        let mut entries = Vec::new();
+        for entry in definition.split(',') {
+            let entry = entry.trim();
+            if entry.is_empty() {
+                continue;
+            }
+            let (days, range) = match entry.split_once(char::is_whitespace) {
+                Some(parts) => parts,
+                None => return Err(format!("missing time range in '{}'", entry)),
+            };
+            let range = range.replace('–', "-").replace(' ', "");
+            let (start, end) = match range.split_once('-') {
+                Some(range) => range,
+                None => return Err(format!("invalid time range '{}'", range)),
+            };
+            entries.push(Entry {
+                days: parse_days(days)?,
+                start: parse_time(start)?,
+                end: parse_time(end)?,
+            });
+        }
+        if entries.is_empty() {
+            return Err("no entries".to_owned());
+        }
+        Ok(Schedule { entries })
+    }
+
+    // Window containing the given time, the one closing last if they overlap
+    pub fn window_at<Tz: TimeZone>(&self, now: DateTime<Tz>) -> Option<Window<Tz>> {
+        self.windows_around(&now)
+            .into_iter()
+            .filter(|window| window.start <= now && now < window.end)
+            .max_by_key(|window| window.end.clone())
+    }
+
+    // First window opening after the given time
+    pub fn next_window<Tz: TimeZone>(&self, now: DateTime<Tz>) -> Option<Window<Tz>> {
+        self.windows_around(&now)
+            .into_iter()
+            .filter(|window| window.start > now)
+            .min_by_key(|window| window.start.clone())
+    }
+
+    // Windows opening from the day before up to a week after the given time
+    // Times that don't exist when clocks go forward have no window, repeated ones use the first
+    fn windows_around<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> Vec<Window<Tz>> {
+        let zone = now.timezone();
+        let today = now.naive_local().date();
+        let mut windows = Vec::new();
+        for offset in -1..=7 {
+            let date = today + Duration::days(offset);
+            let weekday = date.weekday().num_days_from_monday() as usize;
+            for entry in self.entries.iter().filter(|entry| entry.days[weekday]) {
+                let end_date = if entry.end <= entry.start {
+                    date.succ()
+                } else {
+                    date
+                };
+                let start = zone
+                    .from_local_datetime(&date.and_time(entry.start))
+                    .earliest();
+                let end = zone
+                    .from_local_datetime(&end_date.and_time(entry.end))
+                    .earliest();
+                if let (Some(start), Some(end)) = (start, end) {
+                    windows.push(Window { start, end });
+                }
+            }
+        }
+        windows
+    }
+}
+
+// Parse days such as "daily", "weekdays", "weekends", "mon", "fri-sun" or "mon/wed"
+fn parse_days(days: &str) -> Result<[bool; 7], String> {
+    let mut selected = [false; 7];
+    for part in days.to_lowercase().split('/') {
+        match part {
+            "daily" | "everyday" => selected = [true; 7],
+            "weekdays" => selected[0..5].iter_mut().for_each(|day| *day = true),
+            "weekends" => selected[5..7].iter_mut().for_each(|day| *day = true),
+            _ => {
+                let (first, last) = part.split_once('-').unwrap_or((part, part));
+                let first = parse_weekday(first)?;
+                let last = parse_weekday(last)?;
+                let mut day = first;
+                loop {
+                    selected[day] = true;
+                    if day == last {
+                        break;
+                    }
+                    day = (day + 1) % 7;
+                }
+            }
+        }
+    }
+    Ok(selected)
+}
+
+// Parse a weekday name into its index from monday
+fn parse_weekday(name: &str) -> Result<usize, String> {
+    match name.parse::<Weekday>() {
+        Ok(weekday) => Ok(weekday.num_days_from_monday() as usize),
+        Err(_) => Err(format!("unknown day '{}'", name)),
+    }
+}
+
+// Parse a time such as "18:00"
+fn parse_time(time: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(time, "%H:%M").map_err(|_| format!("invalid time '{}'", time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, LocalResult, NaiveDate, NaiveDateTime};
+
+    // UTC in winter, an hour ahead from 2021-03-28 01:00 to 2021-10-31 01:00 UTC, like Lisbon
+    #[derive(Clone, Copy, Debug)]
+    struct Lisbon;
+
+    impl TimeZone for Lisbon {
+        type Offset = FixedOffset;
+
+        fn from_offset(_: &FixedOffset) -> Lisbon {
+            Lisbon
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_hms(12, 0, 0))
+        }
+
+        fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+            // Offsets that turn the local time back into itself, summer time first
+            let offsets = [FixedOffset::east(3600), FixedOffset::east(0)]
+                .iter()
+                .copied()
+                .filter(|offset| self.offset_from_utc_datetime(&(*local - *offset)) == *offset)
+                .collect::<Vec<FixedOffset>>();
+            match offsets[..] {
+                [offset] => LocalResult::Single(offset),
+                [earliest, latest] => LocalResult::Ambiguous(earliest, latest),
+                _ => LocalResult::None,
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_hms(0, 0, 0))
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+            let summer = NaiveDate::from_ymd(2021, 3, 28).and_hms(1, 0, 0)
+                ..NaiveDate::from_ymd(2021, 10, 31).and_hms(1, 0, 0);
+            if summer.contains(utc) {
+                FixedOffset::east(3600)
+            } else {
+                FixedOffset::east(0)
+            }
+        }
+    }
+
+    fn utc(day: u32, hour: u32, minute: u32) -> DateTime<FixedOffset> {
+        // 2021-05-03 is a monday
+        FixedOffset::east(0)
+            .ymd(2021, 5, day)
+            .and_hms(hour, minute, 0)
+    }
+
+    #[test]
+    fn parses_days_and_ranges() {
+        let days = |definition| Schedule::parse(definition).unwrap().entries[0].days;
+        let f = false;
+        assert_eq!(days("daily 10:00-12:00"), [true; 7]);
+        assert_eq!(
+            days("weekdays 10:00-12:00"),
+            [true, true, true, true, true, f, f]
+        );
+        assert_eq!(days("weekends 10:00-12:00"), [f, f, f, f, f, true, true]);
+        assert_eq!(days("tue-thu 10:00-12:00"), [f, true, true, true, f, f, f]);
+        assert_eq!(
+            days("fri-mon 10:00-12:00"),
+            [true, f, f, f, true, true, true]
+        );
+        assert_eq!(days("Mon/Wed 10:00-12:00"), [true, f, true, f, f, f, f]);
+
+        assert!(Schedule::parse("").is_err());
+        assert!(Schedule::parse("daily").is_err());
+        assert!(Schedule::parse("funday 10:00-12:00").is_err());
+        assert!(Schedule::parse("daily 10:00").is_err());
+        assert!(Schedule::parse("daily 25:00-26:00").is_err());
+    }
+
+    #[test]
+    fn parses_en_dash_ranges() {
+        for definition in &[
+            "daily 18:00–23:30",
+            "daily 18:00 – 23:30",
+            "daily 18:00 - 23:30",
+        ] {
+            let schedule = Schedule::parse(definition).unwrap();
+            assert_eq!(schedule.entries[0].start, NaiveTime::from_hms(18, 0, 0));
+            assert_eq!(schedule.entries[0].end, NaiveTime::from_hms(23, 30, 0));
+        }
+    }
+
+    #[test]
+    fn finds_current_and_next_windows() {
+        let schedule = Schedule::parse("weekdays 18:00-23:30, weekends 10:00-02:00").unwrap();
+
+        // Friday afternoon, then friday night
+        assert!(schedule.window_at(utc(7, 15, 0)).is_none());
+        let window = schedule.next_window(utc(7, 15, 0)).unwrap();
+        assert_eq!((window.start, window.end), (utc(7, 18, 0), utc(7, 23, 30)));
+        let window = schedule.window_at(utc(7, 20, 0)).unwrap();
+        assert_eq!((window.start, window.end), (utc(7, 18, 0), utc(7, 23, 30)));
+        let window = schedule.next_window(utc(7, 23, 45)).unwrap();
+        assert_eq!((window.start, window.end), (utc(8, 10, 0), utc(9, 2, 0)));
+    }
+
+    #[test]
+    fn windows_cross_midnight() {
+        let schedule = Schedule::parse("sun 22:00-02:00").unwrap();
+
+        // Monday early morning is still in sunday's window
+        let window = schedule.window_at(utc(10, 1, 0)).unwrap();
+        assert_eq!((window.start, window.end), (utc(9, 22, 0), utc(10, 2, 0)));
+        assert!(schedule.window_at(utc(10, 2, 0)).is_none());
+        let window = schedule.next_window(utc(10, 2, 0)).unwrap();
+        assert_eq!(window.start, utc(16, 22, 0));
+    }
+
+    #[test]
+    fn overlapping_windows_last_until_the_latest_end() {
+        let schedule = Schedule::parse("daily 10:00-12:00, mon 11:00-14:00").unwrap();
+        let window = schedule.window_at(utc(3, 11, 30)).unwrap();
+        assert_eq!((window.start, window.end), (utc(3, 11, 0), utc(3, 14, 0)));
+    }
+
+    #[test]
+    fn follows_daylight_saving_time() {
+        let schedule = Schedule::parse("daily 01:30-03:00").unwrap();
+        let at = |month, day, hour, minute| {
+            Lisbon
+                .ymd(2021, month, day)
+                .and_hms_opt(hour, minute, 0)
+                .unwrap()
+        };
+
+        // 01:30 doesn't exist when clocks go forward, so there's no window that night
+        let window = schedule.next_window(at(3, 28, 0, 0)).unwrap();
+        assert_eq!(window.start, at(3, 29, 1, 30));
+
+        // When clocks go back the window opens at the first 01:30 and lasts an hour longer
+        let window = schedule.next_window(at(10, 31, 0, 0)).unwrap();
+        assert_eq!(window.start.offset(), &FixedOffset::east(3600));
+        assert_eq!(window.end - window.start, Duration::minutes(150));
+        assert!(schedule
+            .window_at(window.start + Duration::hours(2))
+            .is_some());
+    }
+}
@@ -0,0 +1,181 @@
+// Secrets, each looked up in order from:
+//
+//   1. an environment variable, such as DISCORD_WEBHOOK_URL
+//   2. credentials.properties, such as discord-webhook=https://discord.com/api/webhooks/...
+//   3. its own file, such as discord.webhook
+//
+// Files holding secrets shouldn't be readable by other users. credentials.properties
+// is ignored when it is, the older single secret files only get a warning.
+use crate::context::Context;
+use crate::properties;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::path::Path;
+
+const CREDENTIALS_PATH: &str = "credentials.properties";
+
+#[derive(Clone, Copy)]
+pub enum Secret {
+    DiscordWebhook,
+    DiscordToken,
+    DashboardToken,
+}
+
+impl Secret {
+    fn variable(self) -> &'static str {
+        match self {
+            Secret::DiscordWebhook => "DISCORD_WEBHOOK_URL",
+            Secret::DiscordToken => "DISCORD_BOT_TOKEN",
+            Secret::DashboardToken => "DASHBOARD_TOKEN",
+        }
+    }
+
+    fn key(self) -> &'static str {
+        match self {
+            Secret::DiscordWebhook => "discord-webhook",
+            Secret::DiscordToken => "discord-token",
+            Secret::DashboardToken => "dashboard-token",
+        }
+    }
+
+    fn path(self) -> &'static str {
+        match self {
+            Secret::DiscordWebhook => "discord.webhook",
+            Secret::DiscordToken => "discord.token",
+            Secret::DashboardToken => "dashboard.token",
+        }
+    }
+}
+
+// The secret, none when it isn't set anywhere
+pub fn get(context: &Context, secret: Secret) -> Option<String> {
+    if let Some(value) = env::var(secret.variable())
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+    {
+        return Some(value.trim().to_owned());
+    }
+
+    let credentials_path = context.path(CREDENTIALS_PATH);
+    if let Some(contents) = read(&credentials_path) {
+        if private(&credentials_path) {
+            if let Some(value) =
+                properties::get(&contents, secret.key()).filter(|value| !value.is_empty())
+            {
+                return Some(value.to_owned());
+            }
+        } else {
+            println!(
+                "[ERROR] Ignoring {} as other users can read it, run `chmod 600 {}`",
+                CREDENTIALS_PATH, CREDENTIALS_PATH
+            );
+        }
+    }
+
+    let path = context.path(secret.path());
+    let value = read(&path)?;
+    if !private(&path) {
+        println!(
+            "[WARN] {} can be read by other users, run `chmod 600 {}`",
+            secret.path(),
+            secret.path()
+        );
+    }
+    Some(value.trim().to_owned()).filter(|value| !value.is_empty())
+}
+
+fn read(path: &Path) -> Option<String> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Some(contents),
+        Err(error) if error.kind() == ErrorKind::NotFound => None,
+        Err(error) => {
+            println!("[WARN] Failed to read {}: {}", path.display(), error);
+            None
+        }
+    }
+}
+
+// Write a file only the owner can read, for files holding secrets such as webhook URLs
+#[cfg(unix)]
+pub fn write_private<P: AsRef<Path>>(path: P, contents: &str) -> io::Result<()> {
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    // Files created before keep their mode otherwise
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    file.write_all(contents.as_bytes())
+}
+
+#[cfg(not(unix))]
+pub fn write_private<P: AsRef<Path>>(path: P, contents: &str) -> io::Result<()> {
+    fs::write(path, contents)
+}
+
+// Only the owner may read or write the file
+#[cfg(unix)]
+pub(crate) fn private(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|metadata| metadata.permissions().mode() & 0o077 == 0)
+}
+
+#[cfg(not(unix))]
+pub(crate) fn private(_path: &Path) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use std::process;
+
+    fn write(path: &Path, contents: &str, mode: u32) {
+        fs::write(path, contents).unwrap();
+        fs::set_permissions(path, fs::Permissions::from_mode(mode)).unwrap();
+    }
+
+    #[test]
+    fn reads_private_files_only() {
+        let root = env::temp_dir().join(format!("launcher-secrets-{}", process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let context = Context {
+            root: root.clone(),
+            ..Context::default()
+        };
+        let credentials = root.join(CREDENTIALS_PATH);
+        let token = root.join(Secret::DiscordToken.path());
+
+        // credentials.properties is skipped when other users can read it
+        write(&credentials, "discord-token=from-credentials\n", 0o644);
+        write(&token, "from-file\n", 0o600);
+        assert_eq!(
+            get(&context, Secret::DiscordToken).as_deref(),
+            Some("from-file")
+        );
+        fs::set_permissions(&credentials, fs::Permissions::from_mode(0o600)).unwrap();
+        assert_eq!(
+            get(&context, Secret::DiscordToken).as_deref(),
+            Some("from-credentials")
+        );
+
+        // The single secret files are still read, with a warning
+        fs::remove_file(&credentials).unwrap();
+        fs::set_permissions(&token, fs::Permissions::from_mode(0o640)).unwrap();
+        assert!(!private(&token));
+        assert_eq!(
+            get(&context, Secret::DiscordToken).as_deref(),
+            Some("from-file")
+        );
+
+        // Written private, even over a file that wasn't
+        write_private(&token, "rewritten").unwrap();
+        assert!(private(&token));
+        assert_eq!(fs::read_to_string(&token).unwrap(), "rewritten");
+        let _ = fs::remove_dir_all(&root);
+    }
+}
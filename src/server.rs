@@ -0,0 +1,139 @@
+use crate::backup;
+use crate::clock::Clock;
+use crate::context::Context;
+use crate::messages;
+use chrono::prelude::{DateTime, Local};
+use serde_json::json;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, ExitStatus, Stdio};
+use std::thread;
+use std::time::Instant;
+
+// How long a stopping server gets to save and exit before it's killed
+const STOP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+
+// Launch server process
+pub fn launch_server(context: &Context) -> io::Result<Child> {
+    launch_server_with(context, &[])
+}
+
+// Launch server process with extra server options, such as --forceUpgrade
+pub fn launch_server_with(context: &Context, server_args: &[&str]) -> io::Result<Child> {
+    context
+        .java()
+        .args(["-Xmx2048M", "-Xms1024M", "-jar", "server.jar"])
+        .args(server_args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+}
+
+// Save and stop the server, then wait for it to exit
+pub fn stop_server(clock: &Clock, process: &mut Child) -> Option<ExitStatus> {
+    // Wait a bit
+    clock.sleep(std::time::Duration::from_secs(5));
+    // Save server
+    write_to_child_process(process.stdin.as_mut().unwrap(), "save-all".to_string());
+    // Wait a bit more
+    clock.sleep(std::time::Duration::from_secs(5));
+    // Stop server
+    write_to_child_process(process.stdin.as_mut().unwrap(), "stop".to_string());
+    // Wait for server process to exit, killing it if it's hung
+    let started = Instant::now();
+    loop {
+        match process.try_wait() {
+            Ok(Some(status)) => {
+                println!("[INFO] Server process exited ({})", status);
+                return Some(status);
+            }
+            Ok(None) if started.elapsed() < STOP_TIMEOUT => {
+                thread::sleep(std::time::Duration::from_secs(1))
+            }
+            Ok(None) => {
+                println!(
+                    "[WARN] Server didn't stop within {} seconds, killing it",
+                    STOP_TIMEOUT.as_secs()
+                );
+                if let Err(error) = process.kill() {
+                    println!("[WARN] Failed to kill server process: {}", error);
+                }
+                return process.wait().ok();
+            }
+            Err(error) => {
+                println!(
+                    "[WARN] Error attempting to wait for server process to exit: {} ",
+                    error
+                );
+                return None;
+            }
+        }
+    }
+}
+
+// Flush the world to disk and copy it into backups
+pub fn backup_server(
+    context: &Context,
+    child_stdin: &mut ChildStdin,
+    level_name: &str,
+) -> Result<PathBuf, String> {
+    println!("[INFO] Backing up '{}'", level_name);
+    write_to_child_process(child_stdin, "save-off".to_string());
+    write_to_child_process(child_stdin, "save-all flush".to_string());
+    // Wait for the save to finish
+    context.clock.sleep(std::time::Duration::from_secs(5));
+    let result = match backup::backup_world(context, level_name) {
+        Ok(path) => {
+            println!("[INFO] Backup saved to {}", path.display());
+            Ok(path)
+        }
+        Err(error) => {
+            println!("[ERROR] Failed to backup '{}': {}", level_name, error);
+            Err(error.to_string())
+        }
+    };
+    write_to_child_process(child_stdin, "save-on".to_string());
+    result
+}
+
+// Send shutdown reminder
+pub fn say_shutdown_reminder(
+    child_stdin: &mut ChildStdin,
+    message: &str,
+    timestamp: DateTime<Local>,
+) {
+    let hover = messages::format(
+        "reminder.hover.shutdown",
+        &[("time", &timestamp.to_string())],
+    );
+    say_reminder(child_stdin, message, &hover);
+}
+
+// Send restart reminder
+pub fn say_restart_reminder(
+    child_stdin: &mut ChildStdin,
+    message: &str,
+    timestamp: DateTime<Local>,
+) {
+    let hover = messages::format(
+        "reminder.hover.restart",
+        &[("time", &timestamp.to_string())],
+    );
+    say_reminder(child_stdin, message, &hover);
+}
+
+// Say a highlighted message to everyone, with details on hover
+fn say_reminder(child_stdin: &mut ChildStdin, message: &str, hover: &str) {
+    let text = json!({
+        "text": message,
+        "color": "#FBA800",
+        "hoverEvent": {"action": "show_text", "contents": {"text": hover}}
+    });
+    write_to_child_process(child_stdin, format!("tellraw @a {}", text));
+}
+
+// Write input text to child process stdin
+pub fn write_to_child_process(child_stdin: &mut ChildStdin, input: String) {
+    let input = input.to_owned() + "\n";
+    let _ = child_stdin.write_all(input.as_bytes());
+}
@@ -0,0 +1,767 @@
+use crate::context::Context;
+use crate::control::{self, Control};
+use crate::error::LauncherError;
+use crate::messages;
+use crate::notify::{Event, Notifiers};
+use crate::output::{self, ServerEvent};
+use crate::ping::{self, ServerStatus};
+use crate::properties;
+use crate::query;
+use crate::rcon::{self, Rcon};
+use crate::report::{SessionEvent, SessionReport, ShutdownReason};
+use crate::restart::RestartPolicy;
+use crate::schedule::Reminders;
+use crate::server;
+use crate::status::{self, SharedStatus};
+use crate::vote::{ExtendVote, VoteOutcome};
+use crate::watchdog::{self, Check, Watchdog};
+use chrono::prelude::{DateTime, Local};
+use chrono::Duration;
+use std::collections::{HashMap, HashSet};
+use std::process::{Child, ExitStatus};
+use std::sync::mpsc::Receiver;
+
+// How long to wait for a status ping answer
+const PING_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+// Running server and everything the monitoring loop keeps track of
+pub struct Session<'a> {
+    context: Context,
+    notifiers: &'a Notifiers,
+    level_name: String,
+    server_version: String,
+    scheduled_time: Option<DateTime<Local>>,
+    reminders: Reminders,
+    restart_policy: RestartPolicy,
+    restart: Option<(DateTime<Local>, String)>,
+    restart_reminders: Reminders,
+    last_heap_check: DateTime<Local>,
+    extend_vote: ExtendVote,
+    online_players: HashSet<String>,
+    peak_players: usize,
+    joined_players: Vec<String>,
+    // Joins, leaves, backups and restarts, in order
+    history: Vec<(DateTime<Local>, SessionEvent)>,
+    // When each online player joined, and the finished stays
+    joined_at: HashMap<String, DateTime<Local>>,
+    play_intervals: Vec<(String, DateTime<Local>, DateTime<Local>)>,
+    process: Child,
+    events: Receiver<ServerEvent>,
+    // Where the server answers Server List Ping
+    ping_address: (String, u16),
+    // Query port, used before Server List Ping when query is enabled
+    query_port: Option<u16>,
+    ping_interval: Duration,
+    next_ping: DateTime<Local>,
+    // Latest ping answer, none while the server doesn't answer
+    server_status: Option<ServerStatus>,
+    // Server answered a ping since it was launched
+    online: bool,
+    // Server answered a ping at any point in the session, restarts included
+    went_online: bool,
+    // Command that prints ticks per second, run through RCON when enabled
+    tps_command: Option<String>,
+    rcon: Option<(u16, String)>,
+    tps: Option<f64>,
+    watchdog: Watchdog,
+    start_time: DateTime<Local>,
+    launch_time: DateTime<Local>,
+    stopped: Option<(ShutdownReason, Option<ExitStatus>)>,
+    status: SharedStatus,
+}
+
+impl<'a> Session<'a> {
+    // Launch the server, settings are read from server.properties contents
+    pub fn launch(
+        context: &Context,
+        notifiers: &'a Notifiers,
+        level_name: &str,
+        server_version: &str,
+        scheduled_time: Option<DateTime<Local>>,
+        contents: &str,
+        status: SharedStatus,
+    ) -> Result<Session<'a>, LauncherError> {
+        println!(
+            "[INFO] Starting '{}' using Minecraft {}",
+            level_name, server_version
+        );
+        let mut process = server::launch_server(context).map_err(LauncherError::Launch)?;
+        let events = output::spawn(process.stdout.take().unwrap());
+        let launch_time = context.clock.now();
+        let restart_policy = RestartPolicy::from_properties(contents);
+        let restart = restart_policy.next_restart(launch_time);
+        if let Some((restart_time, reason)) = &restart {
+            println!("[INFO] Restart scheduled for {} ({})", restart_time, reason);
+        }
+
+        Ok(Session {
+            context: context.clone(),
+            notifiers,
+            level_name: level_name.to_owned(),
+            server_version: server_version.to_owned(),
+            scheduled_time,
+            reminders: Reminders::shutdown(),
+            restart_policy,
+            restart,
+            restart_reminders: Reminders::restart(),
+            last_heap_check: launch_time,
+            extend_vote: ExtendVote::new(
+                context,
+                properties::get_duration(contents, "extend-amount", Duration::minutes(30)),
+                properties::get_duration(contents, "extend-daily-cap", Duration::hours(2)),
+                properties::get_number(contents, "extend-vote-majority", 50),
+            ),
+            online_players: HashSet::new(),
+            peak_players: 0,
+            joined_players: Vec::new(),
+            history: Vec::new(),
+            joined_at: HashMap::new(),
+            play_intervals: Vec::new(),
+            process,
+            events,
+            ping_address: ping::address(contents),
+            query_port: query::port(contents),
+            ping_interval: properties::get_duration(
+                contents,
+                "ping-interval",
+                Duration::seconds(30),
+            ),
+            next_ping: launch_time,
+            server_status: None,
+            online: false,
+            went_online: false,
+            tps_command: properties::get(contents, "metrics-tps-command").map(str::to_owned),
+            rcon: rcon::config(contents),
+            tps: None,
+            watchdog: Watchdog::from_properties(contents, launch_time),
+            start_time: launch_time,
+            launch_time,
+            stopped: None,
+            status,
+        })
+    }
+
+    // Supervise the server until it exits or is stopped
+    pub fn supervise(&mut self, controls: &Receiver<Control>) -> SessionReport {
+        loop {
+            // Check if server process has exited
+            match self.process.try_wait() {
+                Ok(Some(status)) => {
+                    // Server process has already exited
+                    println!("[INFO] Server process has already exited! ({})", status);
+                    let reason = if control::terminating() {
+                        ShutdownReason::Signal
+                    } else if status.success() {
+                        ShutdownReason::Manual
+                    } else {
+                        ShutdownReason::Crash
+                    };
+                    self.stopped = Some((reason, Some(status)));
+                }
+                Ok(None) => {
+                    // Server process is still running
+
+                    // Stop on termination signals
+                    if control::terminating() {
+                        self.say_shutdown_reminder(&messages::get("say.closing"));
+                        self.stop(ShutdownReason::Signal);
+                    }
+
+                    // Handle console input
+                    while self.stopped.is_none() {
+                        match controls.try_recv() {
+                            Ok(control) => self.handle_control(control),
+                            Err(_) => break,
+                        }
+                    }
+
+                    // Handle server events
+                    while let Ok(event) = self.events.try_recv() {
+                        self.handle_event(event);
+                    }
+
+                    // Check if the server answers and the current time
+                    if self.stopped.is_none() {
+                        self.check_ping();
+                        self.check_watchdog();
+                        self.check_schedule();
+                    }
+                }
+                Err(error) => println!(
+                    "[WARN] Error attempting to wait for server process: {}",
+                    error
+                ),
+            }
+
+            self.publish_status();
+
+            if let Some((reason, exit_status)) = self.stopped {
+                let end_time = self.context.clock.now();
+                self.all_left(end_time);
+                return SessionReport {
+                    level_name: self.level_name.clone(),
+                    world_path: self.context.path(&self.level_name),
+                    server_version: self.server_version.clone(),
+                    start_time: self.start_time,
+                    end_time,
+                    scheduled_time: self.scheduled_time,
+                    reason,
+                    exit_status,
+                    peak_players: self.peak_players,
+                    joined_players: self.joined_players.clone(),
+                    events: std::mem::take(&mut self.history),
+                    play_intervals: std::mem::take(&mut self.play_intervals),
+                };
+            }
+
+            self.context.clock.sleep(std::time::Duration::from_secs(1)); // Sleep a bit before next check
+        }
+    }
+
+    // Whether the server ever accepted connections, a crash before that points at the jar
+    pub fn went_online(&self) -> bool {
+        self.went_online
+    }
+
+    fn handle_control(&mut self, control: Control) {
+        let now = self.context.clock.now();
+        match control {
+            Control::ServerCommand(command) => {
+                server::write_to_child_process(self.process.stdin.as_mut().unwrap(), command)
+            }
+            Control::Status => {
+                println!(
+                    "[INFO] '{}' running Minecraft {} (pid {})",
+                    self.level_name,
+                    self.server_version,
+                    self.process.id()
+                );
+                println!(
+                    "[INFO] Up for {}",
+                    control::format_duration(now - self.launch_time)
+                );
+                match self.scheduled_time {
+                    Some(scheduled_time) => println!(
+                        "[INFO] Shutdown scheduled for {} ({} left)",
+                        scheduled_time,
+                        control::format_duration(scheduled_time - now)
+                    ),
+                    None => println!("[INFO] No shutdown scheduled"),
+                }
+                if let Some((restart_time, reason)) = &self.restart {
+                    println!("[INFO] Restart scheduled for {} ({})", restart_time, reason);
+                }
+                match &self.server_status {
+                    Some(server_status) => {
+                        println!(
+                            "[INFO] Answering pings in {}ms, version '{}' (protocol {}), motd '{}'",
+                            server_status.latency.as_millis(),
+                            server_status.version,
+                            server_status.protocol,
+                            server_status.motd
+                        );
+                        println!(
+                            "[INFO] {} of {} players online: {}",
+                            server_status.online_players,
+                            server_status.max_players,
+                            server_status.sample.join(", ")
+                        );
+                    }
+                    None => println!("[INFO] Not answering pings"),
+                }
+                if let Some(query_port) = self.query_port {
+                    match query::basic_stat(&self.ping_address.0, query_port, PING_TIMEOUT) {
+                        Ok(stat) => println!(
+                            "[INFO] Query: '{}' {} of {} players, {} on map '{}', listening on {}:{}",
+                            stat.motd,
+                            stat.online_players,
+                            stat.max_players,
+                            stat.game_type,
+                            stat.map,
+                            stat.host_ip,
+                            stat.host_port
+                        ),
+                        Err(error) => println!("[INFO] Not answering query: {}", error),
+                    }
+                }
+            }
+            Control::Extend(duration) => {
+                if self.extend(duration) {
+                    self.say_shutdown_reminder(&messages::format(
+                        "say.postponed",
+                        &[("duration", &control::format_duration(duration))],
+                    ));
+                }
+            }
+            Control::Backup => {
+                let started = std::time::Instant::now();
+                let result = server::backup_server(
+                    &self.context,
+                    self.process.stdin.as_mut().unwrap(),
+                    &self.level_name,
+                );
+                status::update(&self.status, |status| {
+                    if result.is_ok() {
+                        status.backups += 1;
+                        status.last_backup_duration = Some(started.elapsed());
+                    } else {
+                        status.failed_backups += 1;
+                    }
+                });
+                self.notifiers.notify(&Event::Backup {
+                    level_name: &self.level_name,
+                    result: &result,
+                });
+                self.history
+                    .push((self.context.clock.now(), SessionEvent::Backup(result)));
+            }
+            Control::Restart => self.restart_server("Requested from console"),
+            Control::Start => println!("[WARN] Server is already running"),
+            Control::Stop => {
+                let message = messages::get("say.closing");
+                println!("[INFO] {}", message);
+                self.say_shutdown_reminder(&message);
+                self.stop(ShutdownReason::Manual);
+            }
+            Control::Reminders => {
+                let reminders = self
+                    .reminders
+                    .iter()
+                    .map(|reminder| ("shutdown", reminder))
+                    .chain(
+                        self.restart_reminders
+                            .iter()
+                            .map(|reminder| ("restart", reminder)),
+                    );
+                for (kind, reminder) in reminders {
+                    println!(
+                        "[INFO] {} before {} ({}): '{}'",
+                        control::format_duration(reminder.before),
+                        kind,
+                        if reminder.sent { "sent" } else { "pending" },
+                        reminder.message
+                    );
+                }
+            }
+            Control::Unknown(line) => {
+                println!("[WARN] Unknown launcher command: '{}'", line);
+                println!("[WARN] Available commands: !status, !extend <duration>, !backup, !restart, !stop, !reminders");
+            }
+        }
+    }
+
+    fn handle_event(&mut self, event: ServerEvent) {
+        let now = self.context.clock.now();
+        self.watchdog.output(now);
+        match event {
+            ServerEvent::Joined(player) => self.joined(player, now),
+            ServerEvent::Left(player) => self.left(player, now),
+            // Done loading, check right away if it accepts connections
+            ServerEvent::Online => self.next_ping = now,
+            ServerEvent::Lagging(behind) => {
+                println!("[WARN] Server can't keep up, running {}ms behind", behind);
+                self.watchdog.lagging(now);
+            }
+            ServerEvent::Tps(tps) => self.tps = Some(tps),
+            ServerEvent::Output => (),
+            ServerEvent::Chat { player, message } => {
+                if !matches!(message.trim(), "!extend" | "!stay") || self.scheduled_time.is_none() {
+                    return;
+                }
+                let message = match self
+                    .extend_vote
+                    .vote(&player, self.online_players.len(), now)
+                {
+                    VoteOutcome::Counted { votes, needed } => messages::format(
+                        "vote.counted",
+                        &[
+                            ("player", &player),
+                            ("votes", &votes.to_string()),
+                            ("needed", &needed.to_string()),
+                        ],
+                    ),
+                    VoteOutcome::Passed(duration) => {
+                        self.extend(duration);
+                        messages::format(
+                            "say.postponed",
+                            &[("duration", &control::format_duration(duration))],
+                        )
+                    }
+                    VoteOutcome::AlreadyVoted => return,
+                    VoteOutcome::CapReached => messages::get("vote.cap-reached"),
+                };
+                println!("[INFO] {}", message);
+                self.say_shutdown_reminder(&message);
+            }
+        }
+    }
+
+    // Push the scheduled shutdown back
+    fn extend(&mut self, duration: Duration) -> bool {
+        let scheduled_time = match control::extended(self.scheduled_time, duration) {
+            Ok(scheduled_time) => scheduled_time,
+            Err(error) => {
+                println!("[WARN] {}", error);
+                return false;
+            }
+        };
+        self.scheduled_time = Some(scheduled_time);
+        self.reminders
+            .rearm(scheduled_time - self.context.clock.now());
+        println!("[INFO] Shutdown rescheduled for {}", scheduled_time);
+        true
+    }
+
+    // Ping the server, announcing it once it first answers
+    fn check_ping(&mut self) {
+        let now = self.context.clock.now();
+        if now < self.next_ping {
+            return;
+        }
+        let (host, port) = self.ping_address.clone();
+        match self.probe() {
+            Ok(server_status) => {
+                // Query lists every player, trust it over the log
+                if let Some(players) = &server_status.players {
+                    let gone = self
+                        .online_players
+                        .iter()
+                        .filter(|player| !players.contains(player))
+                        .cloned()
+                        .collect::<Vec<String>>();
+                    for player in gone {
+                        self.left(player, now);
+                    }
+                    for player in players {
+                        if !self.online_players.contains(player) {
+                            self.joined(player.clone(), now);
+                        }
+                    }
+                }
+                if !self.online {
+                    self.online = true;
+                    self.went_online = true;
+                    println!(
+                        "[INFO] Server is accepting connections on {}:{} ({})",
+                        host, port, server_status.version
+                    );
+                    self.notifiers.notify(&Event::Online {
+                        level_name: &self.level_name,
+                        server_version: &server_status.version,
+                        motd: &server_status.motd,
+                        max_players: server_status.max_players,
+                    });
+                }
+                self.server_status = Some(server_status);
+                self.watchdog.ping(true);
+                self.check_tps();
+            }
+            Err(error) => {
+                if self.online {
+                    println!("[WARN] Server didn't answer ping: {}", error);
+                    self.watchdog.ping(false);
+                }
+                self.server_status = None;
+            }
+        }
+
+        // Poll quickly until the server is up
+        self.next_ping = now
+            + if self.online {
+                self.ping_interval
+            } else {
+                Duration::seconds(5)
+            };
+    }
+
+    // Ask the server for its ticks per second, the answer is parsed from RCON or the log
+    fn check_tps(&mut self) {
+        let command = match &self.tps_command {
+            Some(command) => command,
+            None => return,
+        };
+        let (port, password) = match &self.rcon {
+            Some(rcon) => rcon,
+            None => {
+                server::write_to_child_process(
+                    self.process.stdin.as_mut().unwrap(),
+                    command.clone(),
+                );
+                return;
+            }
+        };
+        match Rcon::connect(&self.ping_address.0, *port, password, PING_TIMEOUT)
+            .and_then(|mut rcon| rcon.command(command))
+        {
+            Ok(response) => match output::parse_tps(&response) {
+                Some(tps) => self.tps = Some(tps),
+                None => println!("[WARN] No TPS in answer to '{}': {}", command, response),
+            },
+            Err(error) => println!("[WARN] Failed to ask for TPS over RCON: {}", error),
+        }
+    }
+
+    // A player connected, seen in the log or the query player list
+    fn joined(&mut self, player: String, now: DateTime<Local>) {
+        if !self.joined_players.contains(&player) {
+            self.joined_players.push(player.clone());
+        }
+        self.online_players.insert(player.clone());
+        self.peak_players = self.peak_players.max(self.online_players.len());
+        self.joined_at.entry(player.clone()).or_insert(now);
+        self.history.push((now, SessionEvent::Joined(player)));
+    }
+
+    // A player disconnected, their vote goes with them
+    fn left(&mut self, player: String, now: DateTime<Local>) {
+        self.extend_vote.withdraw(&player);
+        self.online_players.remove(&player);
+        self.player_left(&player, now);
+        self.history.push((now, SessionEvent::Left(player)));
+    }
+
+    // Close the stay of a player who went offline
+    fn player_left(&mut self, player: &str, now: DateTime<Local>) {
+        if let Some(joined_at) = self.joined_at.remove(player) {
+            self.play_intervals
+                .push((player.to_owned(), joined_at, now));
+        }
+    }
+
+    // Everyone is disconnected when the server goes down
+    fn all_left(&mut self, now: DateTime<Local>) {
+        let players = self.joined_at.keys().cloned().collect::<Vec<String>>();
+        for player in players {
+            self.player_left(&player, now);
+        }
+    }
+
+    // Look for a server that is running but stuck
+    fn check_watchdog(&mut self) {
+        // Loading the world can take a while, only watch a server that was up
+        if !self.online {
+            return;
+        }
+        let reason = match self.watchdog.check(self.context.clock.now()) {
+            Some(Check::Probe) => {
+                server::write_to_child_process(
+                    self.process.stdin.as_mut().unwrap(),
+                    watchdog::PROBE_COMMAND.to_owned(),
+                );
+                return;
+            }
+            Some(Check::Hung(reason)) => reason,
+            Some(Check::Lagging(reason)) => {
+                println!("[WARN] Server is overloaded: {}", reason);
+                return;
+            }
+            None => return,
+        };
+        println!("[ERROR] Server looks hung: {}", reason);
+
+        let thread_dump = match watchdog::thread_dump(&self.context, self.process.id()) {
+            Ok(path) => {
+                println!("[INFO] Thread dump saved to {}", path.display());
+                Some(path)
+            }
+            Err(error) => {
+                println!("[WARN] Failed to take a thread dump: {}", error);
+                None
+            }
+        };
+        self.notifiers.notify(&Event::Hung {
+            reason: &reason,
+            thread_dump: thread_dump.as_deref(),
+        });
+
+        if self.restart_policy.restarts_on_hang() {
+            self.restart_server(&reason);
+        }
+    }
+
+    // Ask the server for its status, through query when enabled
+    fn probe(&self) -> std::io::Result<ServerStatus> {
+        let (host, port) = &self.ping_address;
+        if let Some(query_port) = self.query_port {
+            match query::status(host, query_port, PING_TIMEOUT) {
+                Ok(server_status) => return Ok(server_status),
+                Err(error) => {
+                    if self.online {
+                        println!(
+                            "[WARN] Server didn't answer query, falling back to ping: {}",
+                            error
+                        );
+                    }
+                }
+            }
+        }
+        ping::ping(host, *port, PING_TIMEOUT)
+    }
+
+    // Run reminders, restarts and the scheduled shutdown
+    fn check_schedule(&mut self) {
+        let now = self.context.clock.now();
+
+        // Scheduled shutdown
+        if let Some(scheduled_time) = self.scheduled_time {
+            if scheduled_time < now {
+                // Time's Up!
+                let message = messages::get("say.times-up");
+                println!("[INFO] {}", message);
+                self.say_shutdown_reminder(&message);
+                self.stop(ShutdownReason::Scheduled);
+                return;
+            } else if let Some(message) = self.reminders.due(scheduled_time - now) {
+                println!("[INFO] {}", message);
+                self.say_shutdown_reminder(&message);
+                self.notifiers.notify(&Event::Reminder {
+                    message: &message,
+                    time: scheduled_time,
+                });
+            }
+        }
+
+        // Heap usage, checked every minute
+        if self.restart_policy.checks_heap() && now - self.last_heap_check >= Duration::minutes(1) {
+            self.last_heap_check = now;
+            if let Some(reason) = self.restart_policy.heap_exceeded(self.process.id()) {
+                let restart_time = now + Duration::minutes(5);
+                if self
+                    .restart
+                    .as_ref()
+                    .is_none_or(|(time, _)| restart_time < *time)
+                {
+                    println!("[INFO] Restart scheduled for {} ({})", restart_time, reason);
+                    self.restart = Some((restart_time, reason));
+                }
+            }
+        }
+
+        // Nobody would notice a restart now
+        if let Some(reason) =
+            self.restart_policy
+                .empty_restart(self.launch_time, now, self.online_players.len())
+        {
+            if self.restart.as_ref().is_none_or(|(time, _)| now < *time) {
+                println!("[INFO] Restart scheduled for {} ({})", now, reason);
+                self.restart = Some((now, reason));
+            }
+        }
+
+        // Scheduled restart, unless the server shuts down before it
+        if let Some((restart_time, reason)) = self.restart.clone() {
+            if self
+                .scheduled_time
+                .is_some_and(|scheduled_time| scheduled_time <= restart_time)
+            {
+                return;
+            }
+            if restart_time < now {
+                self.restart_server(&reason);
+            } else if let Some(message) = self.restart_reminders.due(restart_time - now) {
+                println!("[INFO] {}", message);
+                server::say_restart_reminder(
+                    self.process.stdin.as_mut().unwrap(),
+                    &message,
+                    restart_time,
+                );
+                self.notifiers.notify(&Event::Reminder {
+                    message: &message,
+                    time: restart_time,
+                });
+            }
+        }
+    }
+
+    // Share the current state with the other threads
+    fn publish_status(&self) {
+        status::update(&self.status, |status| {
+            status.running = self.stopped.is_none();
+            status.level_name = self.level_name.clone();
+            status.server_version = self.server_version.clone();
+            status.launch_time = Some(self.launch_time);
+            status.scheduled_time = self.scheduled_time;
+            status.restart_time = self.restart.as_ref().map(|(restart_time, _)| *restart_time);
+            status.next_session = None;
+            status.online_players = self.online_players.iter().cloned().collect();
+            status.online_players.sort();
+            status.server_status = self.server_status.clone();
+            status.pid = Some(self.process.id()).filter(|_| self.stopped.is_none());
+            status.tps = self.tps;
+        });
+        if self.stopped.is_none() {
+            self.notifiers.update(&status::snapshot(&self.status));
+        }
+    }
+
+    // Save and stop the server, ending the session
+    fn stop(&mut self, reason: ShutdownReason) {
+        let exit_status = server::stop_server(&self.context.clock, &mut self.process);
+        self.stopped = Some((reason, exit_status));
+    }
+
+    // Stop and relaunch the server, keeping the lock
+    fn restart_server(&mut self, reason: &str) {
+        let message = messages::get("say.restarting");
+        println!("[INFO] {} ({})", message, reason);
+        server::say_restart_reminder(
+            self.process.stdin.as_mut().unwrap(),
+            &message,
+            self.context.clock.now(),
+        );
+        server::stop_server(&self.context.clock, &mut self.process);
+
+        // Send restarting notification
+        self.notifiers.notify(&Event::Restarting { reason });
+        self.history.push((
+            self.context.clock.now(),
+            SessionEvent::Restarted(reason.to_owned()),
+        ));
+
+        // Relaunch server process, the session ends as a crash if that fails
+        self.process = match server::launch_server(&self.context) {
+            Ok(process) => process,
+            Err(error) => {
+                println!("[ERROR] Running process error: {}", error);
+                let exit_status = self.process.try_wait().ok().flatten();
+                self.stopped = Some((ShutdownReason::Crash, exit_status));
+                return;
+            }
+        };
+        self.events = output::spawn(self.process.stdout.take().unwrap());
+        self.launch_time = self.context.clock.now();
+        self.online_players.clear();
+        self.all_left(self.launch_time);
+        self.next_ping = self.launch_time;
+        self.server_status = None;
+        self.online = false;
+        self.tps = None;
+        status::update(&self.status, |status| status.restarts += 1);
+        self.watchdog.reset(self.launch_time);
+        self.restart = self.restart_policy.next_restart(self.launch_time);
+        self.restart_reminders = Reminders::restart();
+        if let Some((restart_time, reason)) = &self.restart {
+            println!("[INFO] Restart scheduled for {} ({})", restart_time, reason);
+        }
+    }
+
+    fn say_shutdown_reminder(&mut self, message: &str) {
+        if let Some(scheduled_time) = self.scheduled_time {
+            server::say_shutdown_reminder(
+                self.process.stdin.as_mut().unwrap(),
+                message,
+                scheduled_time,
+            );
+        }
+    }
+}
+
+// A session dropped while the server runs, such as during a panic, stops the server first
+impl<'a> Drop for Session<'a> {
+    fn drop(&mut self) {
+        if let Ok(None) = self.process.try_wait() {
+            println!("[WARN] Stopping the server before leaving");
+            server::stop_server(&self.context.clock, &mut self.process);
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use crate::ping::ServerStatus;
+use chrono::prelude::{DateTime, Local};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Snapshot of the launcher state, shared with the threads serving it to others
+#[derive(Clone, Default)]
+pub struct Status {
+    pub running: bool,
+    pub level_name: String,
+    pub server_version: String,
+    pub launch_time: Option<DateTime<Local>>,
+    pub scheduled_time: Option<DateTime<Local>>,
+    pub restart_time: Option<DateTime<Local>>,
+    pub next_session: Option<DateTime<Local>>,
+    pub online_players: Vec<String>,
+    // Latest Server List Ping answer
+    pub server_status: Option<ServerStatus>,
+    // Server process, while running
+    pub pid: Option<u32>,
+    // Latest ticks per second reported by the server
+    pub tps: Option<f64>,
+    // Counters since the launcher started
+    pub restarts: u64,
+    pub backups: u64,
+    pub failed_backups: u64,
+    pub last_backup_duration: Option<Duration>,
+}
+
+pub type SharedStatus = Arc<Mutex<Status>>;
+
+// Read the latest snapshot
+pub fn snapshot(status: &SharedStatus) -> Status {
+    match status.lock() {
+        Ok(status) => status.clone(),
+        Err(poisoned) => poisoned.into_inner().clone(),
+    }
+}
+
+// Update the snapshot in place
+pub fn update<F: FnOnce(&mut Status)>(status: &SharedStatus, update: F) {
+    match status.lock() {
+        Ok(mut status) => update(&mut status),
+        Err(poisoned) => update(&mut poisoned.into_inner()),
+    }
+}
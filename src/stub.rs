@@ -0,0 +1,116 @@
+// Local HTTP server standing in for remote APIs in tests, the integration tests too
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+// Status, headers and body of a canned response
+pub type Response = (u16, Vec<(&'static str, String)>, String);
+
+pub struct Stub {
+    pub url: String,
+    requests: Receiver<Request>,
+}
+
+impl Stub {
+    // Serve the given responses in order, then 200 with an empty body
+    pub fn start(responses: Vec<Response>) -> Stub {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, requests) = mpsc::channel();
+        thread::spawn(move || {
+            let mut responses = responses.into_iter();
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => break,
+                };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                // Read request line and headers
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let mut parts = line.split_whitespace();
+                let method = parts.next().unwrap_or_default().to_owned();
+                let path = parts.next().unwrap_or_default().to_owned();
+                let mut headers = Vec::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    match line.trim_end().split_once(": ") {
+                        Some((name, value)) => {
+                            headers.push((name.to_lowercase(), value.to_owned()))
+                        }
+                        None => break,
+                    }
+                }
+
+                // Read body
+                let length = headers
+                    .iter()
+                    .find(|(name, _)| name == "content-length")
+                    .map(|(_, value)| value.parse::<usize>().unwrap())
+                    .unwrap_or(0);
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+
+                // Answer
+                let (status, response_headers, response_body) =
+                    responses.next().unwrap_or((200, Vec::new(), String::new()));
+                let mut response = format!(
+                    "HTTP/1.1 {} Stub\r\nContent-Length: {}\r\nContent-Type: application/json\r\nConnection: close\r\n",
+                    status,
+                    response_body.len()
+                );
+                for (name, value) in response_headers {
+                    response += &format!("{}: {}\r\n", name, value);
+                }
+                response += "\r\n";
+                response += &response_body;
+                let _ = stream.write_all(response.as_bytes());
+
+                let _ = sender.send(Request {
+                    method,
+                    path,
+                    headers,
+                    body: String::from_utf8_lossy(&body).into_owned(),
+                });
+            }
+        });
+        Stub { url, requests }
+    }
+
+    // Wait for the next request the stub answered
+    pub fn next_request(&self) -> Request {
+        self.requests
+            .recv_timeout(Duration::from_secs(5))
+            .expect("no request received")
+    }
+
+    // Every request answered so far
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests
+            .recv_timeout(Duration::from_millis(200))
+            .into_iter()
+            .chain(std::iter::from_fn(|| {
+                self.requests.recv_timeout(Duration::from_millis(200)).ok()
+            }))
+            .collect()
+    }
+
+    // Check that nothing else was requested
+    pub fn assert_no_more_requests(&self) {
+        assert!(self
+            .requests
+            .recv_timeout(Duration::from_millis(200))
+            .is_err());
+    }
+}
@@ -0,0 +1,208 @@
+// `upgrade <version>`, move the world to another Minecraft version
+use crate::backup;
+use crate::cleanup::ServerLock;
+use crate::context::Context;
+use crate::error::LauncherError;
+use crate::jar;
+use crate::notify::{Event, Notifiers};
+use crate::output::{self, ServerEvent};
+use crate::properties;
+use crate::server;
+use crate::world::{self, WorldInfo};
+use std::cmp::Ordering;
+use std::fs;
+use std::thread;
+
+const USAGE: &str = "upgrade <version> [--force] [--force-upgrade] [--erase-cache]";
+
+// `upgrade <version> [--force] [--force-upgrade] [--erase-cache]`
+//
+// Refuses downgrades unless --force is given, backs the world up and points
+// server-version at the new jar. --force-upgrade and --erase-cache start the
+// new version once with the matching server options to convert every chunk.
+pub fn run(context: &Context, notifiers: &Notifiers, args: &[String]) -> Result<(), LauncherError> {
+    let mut to_version = None;
+    let mut force = false;
+    let mut server_args = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--force" => force = true,
+            "--force-upgrade" => server_args.push("--forceUpgrade"),
+            "--erase-cache" => server_args.push("--eraseCache"),
+            _ if arg.starts_with("--") || to_version.is_some() => {
+                println!("[ERROR] Unknown option '{}'", arg);
+                return Err(LauncherError::Usage(USAGE.to_owned()));
+            }
+            _ => to_version = Some(arg.as_str()),
+        }
+    }
+    let to_version = match to_version {
+        Some(to_version) => to_version,
+        None => return Err(LauncherError::Usage(USAGE.to_owned())),
+    };
+    // Erasing the cache only happens during a forced upgrade
+    if server_args == ["--eraseCache"] {
+        server_args.insert(0, "--forceUpgrade");
+    }
+
+    let contents = match fs::read_to_string(context.path("server.properties")) {
+        Ok(contents) => contents,
+        Err(error) => {
+            return Err(LauncherError::Config(format!(
+                "Failed to open server.properties: {}",
+                error
+            )))
+        }
+    };
+    let (level_name, from_version) = match (
+        properties::get(&contents, "level-name"),
+        properties::get(&contents, "server-version"),
+    ) {
+        (Some(level_name), Some(from_version)) => (level_name, from_version),
+        _ => {
+            return Err(LauncherError::Config(
+                "level-name and server-version must be in server.properties".to_owned(),
+            ))
+        }
+    };
+    if from_version == to_version && server_args.is_empty() {
+        println!("[INFO] Server is already on {}", to_version);
+        return Ok(());
+    }
+    let jar_path = context.path(format!("jars/{}.jar", to_version));
+    if !jar_path.is_file() {
+        return Err(LauncherError::Jar(format!(
+            "{} not found",
+            jar_path.display()
+        )));
+    }
+
+    // Worlds can't be opened by versions older than the one that last saved them
+    let world_version = match WorldInfo::load(&context.path(level_name)) {
+        Ok(world) => world.data_version,
+        Err(error) => {
+            println!("[WARN] Failed to read {}/level.dat: {}", level_name, error);
+            None
+        }
+    };
+    let jar_version = world::jar_data_version(&jar_path);
+    let downgrade = match (world_version, jar_version) {
+        (Some(world_version), Some(jar_version)) => {
+            println!(
+                "[INFO] World data version {}, {} uses {}",
+                world_version, to_version, jar_version
+            );
+            Some(jar_version < world_version)
+        }
+        _ => compare_versions(from_version, to_version).map(|order| order == Ordering::Greater),
+    };
+    match downgrade {
+        Some(true) if !force => {
+            return Err(LauncherError::Incompatible(format!(
+                "{} is older than the world, use --force to downgrade anyway",
+                to_version
+            )))
+        }
+        Some(true) => println!(
+            "[WARN] Downgrading to {}, the world may not load, launch with --force once",
+            to_version
+        ),
+        Some(false) => (),
+        None => println!(
+            "[WARN] Couldn't tell whether {} is older than the world",
+            to_version
+        ),
+    }
+
+    // Hold the lock so nobody launches the server halfway through
+    let lock = ServerLock::acquire(context)?;
+    let backup = backup::backup_world(context, level_name).map_err(LauncherError::Backup)?;
+    println!("[INFO] World saved to {}", backup.display());
+
+    // server.jar.previous may be left from an earlier session, so only a jar
+    // this run put in place is rolled back
+    let mut installed = false;
+    let result = if server_args.is_empty() {
+        Ok(())
+    } else {
+        match jar::install(context, to_version) {
+            Ok(()) => {
+                installed = true;
+                upgrade_world(context, &server_args)
+            }
+            Err(error) => Err(error),
+        }
+    };
+    let result = result.and_then(|()| {
+        let contents = properties::set(&contents, "server-version", to_version);
+        let temporary_path = context.path("server.properties.tmp");
+        fs::write(&temporary_path, contents)
+            .and_then(|()| fs::rename(&temporary_path, context.path("server.properties")))
+            .map_err(|error| format!("Failed to update server.properties: {}", error))
+    });
+    if result.is_err() && installed {
+        jar::restore(context);
+    }
+    drop(lock);
+
+    match &result {
+        Ok(()) => println!(
+            "[INFO] Upgraded '{}' from {} to {}",
+            level_name, from_version, to_version
+        ),
+        Err(_) => println!("[WARN] The world backup is in {}", backup.display()),
+    }
+    notifiers.notify(&Event::Upgrade {
+        level_name,
+        from_version,
+        to_version,
+        backup: &backup,
+        result: &result,
+    });
+    result.map_err(LauncherError::Upgrade)
+}
+
+// Run the installed jar with the given server options until the world is loaded
+fn upgrade_world(context: &Context, server_args: &[&str]) -> Result<(), String> {
+    println!("[INFO] Starting the server with {}", server_args.join(" "));
+    let mut process = match server::launch_server_with(context, server_args) {
+        Ok(process) => process,
+        Err(error) => return Err(format!("Running process error: {}", error)),
+    };
+    let events = output::spawn(process.stdout.take().unwrap());
+
+    // Converting every chunk can take a long while on big worlds
+    loop {
+        while let Ok(event) = events.try_recv() {
+            if let ServerEvent::Online = event {
+                println!("[INFO] World upgraded, stopping the server");
+                return match server::stop_server(&context.clock, &mut process) {
+                    Some(status) if status.success() => Ok(()),
+                    Some(status) => Err(format!("Server exited with {}", status)),
+                    None => Err("Server didn't stop".to_owned()),
+                };
+            }
+        }
+        match process.try_wait() {
+            Ok(Some(status)) => {
+                return Err(format!(
+                    "Server exited with {} before loading the world",
+                    status
+                ))
+            }
+            Ok(None) => thread::sleep(std::time::Duration::from_secs(1)),
+            Err(error) => return Err(format!("Failed to check the server: {}", error)),
+        }
+    }
+}
+
+// Order of release versions such as 1.16.5, none for snapshots and the like
+pub fn compare_versions(a: &str, b: &str) -> Option<Ordering> {
+    let parse = |version: &str| {
+        version
+            .split('.')
+            .map(|part| part.parse::<u32>().ok())
+            .collect::<Option<Vec<u32>>>()
+    };
+    Some(parse(a)?.cmp(&parse(b)?))
+}
@@ -0,0 +1,222 @@
+use crate::context::Context;
+use chrono::prelude::{DateTime, Local};
+use chrono::{Duration, NaiveDate};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Outcome of a player asking to extend the session
+#[derive(Debug, PartialEq)]
+pub enum VoteOutcome {
+    // Vote counted, more are needed
+    Counted { votes: usize, needed: usize },
+    // Session extended by the given amount
+    Passed(Duration),
+    // Player already voted
+    AlreadyVoted,
+    // Daily cap reached, nothing to extend
+    CapReached,
+}
+
+// In-game vote to push the scheduled shutdown back
+pub struct ExtendVote {
+    amount: Duration,
+    daily_cap: Duration,
+    majority: u32,
+    votes: HashSet<String>,
+    day: NaiveDate,
+    extended_today: Duration,
+    // Ops get their way without a majority, read from ops.json when a vote starts
+    ops_path: PathBuf,
+    ops: HashSet<String>,
+}
+
+impl ExtendVote {
+    // amount: extension per passed vote
+    // daily_cap: total extension allowed per day
+    // majority: percentage of online players that must vote
+    pub fn new(
+        context: &Context,
+        amount: Duration,
+        daily_cap: Duration,
+        majority: u32,
+    ) -> ExtendVote {
+        ExtendVote {
+            amount,
+            daily_cap,
+            majority,
+            votes: HashSet::new(),
+            day: context.clock.now().naive_local().date(),
+            extended_today: Duration::zero(),
+            ops_path: context.path("ops.json"),
+            ops: HashSet::new(),
+        }
+    }
+
+    // Count a vote from a player, ops get their way immediately
+    pub fn vote(
+        &mut self,
+        player: &str,
+        online_players: usize,
+        now: DateTime<Local>,
+    ) -> VoteOutcome {
+        if self.day != now.naive_local().date() {
+            self.day = now.naive_local().date();
+            self.extended_today = Duration::zero();
+        }
+        let amount = self.amount.min(self.daily_cap - self.extended_today);
+        if amount <= Duration::zero() {
+            return VoteOutcome::CapReached;
+        }
+        if self.votes.is_empty() {
+            self.ops = load_ops(&self.ops_path);
+        }
+        if !self.votes.insert(player.to_owned()) {
+            return VoteOutcome::AlreadyVoted;
+        }
+
+        let online_players = online_players.max(1);
+        let needed = ((online_players * self.majority as usize) / 100 + 1).min(online_players);
+        if self.votes.len() < needed && !self.ops.contains(player) {
+            return VoteOutcome::Counted {
+                votes: self.votes.len(),
+                needed,
+            };
+        }
+
+        self.votes.clear();
+        self.extended_today = self.extended_today + amount;
+        VoteOutcome::Passed(amount)
+    }
+
+    // Drop the vote of a player who left
+    pub fn withdraw(&mut self, player: &str) {
+        self.votes.remove(player);
+    }
+}
+
+// Names of the players listed in ops.json
+fn load_ops(ops_path: &Path) -> HashSet<String> {
+    let contents = match fs::read_to_string(ops_path) {
+        Ok(contents) => contents,
+        Err(_) => return HashSet::new(),
+    };
+    match serde_json::from_str::<serde_json::Value>(&contents) {
+        Ok(serde_json::Value::Array(ops)) => ops
+            .iter()
+            .filter_map(|op| op["name"].as_str())
+            .map(str::to_owned)
+            .collect(),
+        _ => {
+            println!("[WARN] Failed to parse ops.json");
+            HashSet::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::env;
+    use std::process;
+
+    fn noon(day: u32) -> DateTime<Local> {
+        Local.ymd(2021, 5, day).and_hms(12, 0, 0)
+    }
+
+    // Vote in a server directory with the given ops.json, if any
+    fn extend_vote(name: &str, ops: Option<&str>, majority: u32) -> ExtendVote {
+        let root = env::temp_dir().join(format!("launcher-vote-{}-{}", process::id(), name));
+        if let Some(ops) = ops {
+            fs::create_dir_all(&root).unwrap();
+            fs::write(root.join("ops.json"), ops).unwrap();
+        }
+        let context = Context {
+            root,
+            ..Context::default()
+        };
+        ExtendVote::new(
+            &context,
+            Duration::minutes(30),
+            Duration::minutes(45),
+            majority,
+        )
+    }
+
+    #[test]
+    fn needs_a_majority_of_online_players() {
+        let mut vote = extend_vote("majority", None, 50);
+        // 4 online, half of them plus one
+        let counted = |votes| VoteOutcome::Counted { votes, needed: 3 };
+        assert_eq!(vote.vote("Steve", 4, noon(1)), counted(1));
+        assert_eq!(vote.vote("Steve", 4, noon(1)), VoteOutcome::AlreadyVoted);
+        assert_eq!(vote.vote("Alex", 4, noon(1)), counted(2));
+        vote.withdraw("Alex");
+        assert_eq!(vote.vote("Herobrine", 4, noon(1)), counted(2));
+        assert_eq!(
+            vote.vote("Alex", 4, noon(1)),
+            VoteOutcome::Passed(Duration::minutes(30))
+        );
+
+        // Never more than everyone online
+        let mut vote = extend_vote("everyone", None, 100);
+        assert_eq!(
+            vote.vote("Steve", 2, noon(1)),
+            VoteOutcome::Counted {
+                votes: 1,
+                needed: 2
+            }
+        );
+        assert_eq!(
+            vote.vote("Alex", 2, noon(1)),
+            VoteOutcome::Passed(Duration::minutes(30))
+        );
+        let mut vote = extend_vote("alone", None, 50);
+        assert_eq!(
+            vote.vote("Steve", 0, noon(1)),
+            VoteOutcome::Passed(Duration::minutes(30))
+        );
+    }
+
+    #[test]
+    fn stops_at_the_daily_cap() {
+        let mut vote = extend_vote("cap", None, 50);
+        assert_eq!(
+            vote.vote("Steve", 1, noon(1)),
+            VoteOutcome::Passed(Duration::minutes(30))
+        );
+        // Only what's left of the cap
+        assert_eq!(
+            vote.vote("Steve", 1, noon(1)),
+            VoteOutcome::Passed(Duration::minutes(15))
+        );
+        assert_eq!(vote.vote("Steve", 1, noon(1)), VoteOutcome::CapReached);
+        // Back the next day
+        assert_eq!(
+            vote.vote("Steve", 1, noon(2)),
+            VoteOutcome::Passed(Duration::minutes(30))
+        );
+    }
+
+    #[test]
+    fn ops_pass_the_vote_alone() {
+        let mut vote = extend_vote(
+            "ops",
+            Some(r#"[{"uuid": "0", "name": "Steve", "level": 4}]"#),
+            50,
+        );
+        assert_eq!(
+            vote.vote("Alex", 10, noon(1)),
+            VoteOutcome::Counted {
+                votes: 1,
+                needed: 6
+            }
+        );
+        assert_eq!(
+            vote.vote("Steve", 10, noon(1)),
+            VoteOutcome::Passed(Duration::minutes(30))
+        );
+        let _ = fs::remove_dir_all(vote.ops_path.parent().unwrap());
+    }
+}
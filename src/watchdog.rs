@@ -0,0 +1,207 @@
+use crate::context::Context;
+use crate::control;
+use crate::properties;
+use chrono::prelude::{DateTime, Local};
+use chrono::Duration;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+// Command sent to a silent server, any output means its main thread is still ticking
+pub const PROBE_COMMAND: &str = "list";
+
+// What the watchdog wants done
+pub enum Check {
+    // Server has been quiet, ask it for output
+    Probe,
+    // Server looks hung, with why
+    Hung(String),
+    // Server is overloaded but still ticking, only worth a warning
+    Lagging(String),
+}
+
+// Notices a server that is still running but no longer working
+pub struct Watchdog {
+    // Consecutive failed pings that mean the server is hung
+    ping_failure_limit: u32,
+    // Quiet time before probing the console
+    silence: Duration,
+    // Time the console gets to answer a probe
+    response_timeout: Duration,
+    // "Can't keep up!" warnings within the window that mean the server is overloaded,
+    // which is warned about but isn't a hang
+    lag_limit: usize,
+    lag_window: Duration,
+    failed_pings: u32,
+    last_output: DateTime<Local>,
+    probe_sent: Option<DateTime<Local>>,
+    lag_warnings: VecDeque<DateTime<Local>>,
+    // Already reported, stays quiet until the server looks fine again
+    tripped: bool,
+    lag_reported: bool,
+}
+
+impl Watchdog {
+    // Read the watchdog-* settings from server.properties
+    pub fn from_properties(contents: &str, now: DateTime<Local>) -> Watchdog {
+        Watchdog {
+            ping_failure_limit: properties::get_number(contents, "watchdog-ping-failures", 3),
+            silence: properties::get_duration(contents, "watchdog-silence", Duration::minutes(5)),
+            response_timeout: properties::get_duration(
+                contents,
+                "watchdog-timeout",
+                Duration::minutes(1),
+            ),
+            lag_limit: properties::get_number(contents, "watchdog-lag-warnings", 5),
+            lag_window: properties::get_duration(
+                contents,
+                "watchdog-lag-window",
+                Duration::minutes(10),
+            ),
+            failed_pings: 0,
+            last_output: now,
+            probe_sent: None,
+            lag_warnings: VecDeque::new(),
+            tripped: false,
+            lag_reported: false,
+        }
+    }
+
+    // Start over, such as after a restart
+    pub fn reset(&mut self, now: DateTime<Local>) {
+        self.failed_pings = 0;
+        self.last_output = now;
+        self.probe_sent = None;
+        self.lag_warnings.clear();
+        self.tripped = false;
+        self.lag_reported = false;
+    }
+
+    // Server printed something
+    pub fn output(&mut self, now: DateTime<Local>) {
+        self.last_output = now;
+        self.probe_sent = None;
+    }
+
+    // Server logged "Can't keep up!"
+    pub fn lagging(&mut self, now: DateTime<Local>) {
+        self.lag_warnings.push_back(now);
+    }
+
+    // Result of a status ping
+    pub fn ping(&mut self, answered: bool) {
+        if answered {
+            self.failed_pings = 0;
+        } else {
+            self.failed_pings += 1;
+        }
+    }
+
+    // Decide whether to probe the console, report a hang or warn about lag
+    // Only missed pings and an unanswered probe count as a hang
+    pub fn check(&mut self, now: DateTime<Local>) -> Option<Check> {
+        while self
+            .lag_warnings
+            .front()
+            .is_some_and(|warning| now - *warning > self.lag_window)
+        {
+            self.lag_warnings.pop_front();
+        }
+
+        let reason = if self.ping_failure_limit > 0 && self.failed_pings >= self.ping_failure_limit
+        {
+            Some(format!(
+                "No answer to {} status pings in a row",
+                self.failed_pings
+            ))
+        } else if let Some(probe_sent) = self.probe_sent {
+            if now - probe_sent < self.response_timeout {
+                return None;
+            }
+            Some(format!(
+                "No console output for {}",
+                control::format_duration(now - self.last_output)
+            ))
+        } else {
+            None
+        };
+
+        match reason {
+            Some(reason) if !self.tripped => {
+                self.tripped = true;
+                Some(Check::Hung(reason))
+            }
+            Some(_) => None,
+            None => {
+                self.tripped = false;
+                if self.lag_limit > 0 && self.lag_warnings.len() >= self.lag_limit {
+                    if !self.lag_reported {
+                        self.lag_reported = true;
+                        return Some(Check::Lagging(format!(
+                            "{} \"Can't keep up!\" warnings in {}",
+                            self.lag_warnings.len(),
+                            control::format_duration(self.lag_window)
+                        )));
+                    }
+                } else {
+                    self.lag_reported = false;
+                }
+                if now - self.last_output >= self.silence {
+                    self.probe_sent = Some(now);
+                    Some(Check::Probe)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+// Save the server's thread stacks, with jstack or else jcmd
+pub fn thread_dump(context: &Context, pid: u32) -> Result<PathBuf, String> {
+    let pid = pid.to_string();
+    let output = Command::new("jstack")
+        .arg(&pid)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .or_else(|| {
+            Command::new("jcmd")
+                .args([&pid[..], "Thread.print"])
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+        })
+        .ok_or_else(|| "neither jstack nor jcmd could dump threads".to_owned())?;
+
+    let directory = context.path("thread-dumps");
+    fs::create_dir_all(&directory).map_err(|error| error.to_string())?;
+    let path = directory.join(format!(
+        "threads-{}.txt",
+        context.clock.now().format("%Y-%m-%d_%H-%M-%S")
+    ));
+    fs::write(&path, &output.stdout).map_err(|error| error.to_string())?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lag_is_a_warning_not_a_hang() {
+        let now = Local::now();
+        let mut watchdog = Watchdog::from_properties("", now);
+        for _ in 0..5 {
+            watchdog.lagging(now);
+        }
+        assert!(matches!(watchdog.check(now), Some(Check::Lagging(_))));
+        assert!(watchdog.check(now).is_none());
+
+        for _ in 0..3 {
+            watchdog.ping(false);
+        }
+        assert!(matches!(watchdog.check(now), Some(Check::Hung(_))));
+    }
+}
@@ -0,0 +1,528 @@
+use crate::secrets;
+use reqwest::blocking::multipart::{Form, Part};
+use serde_json::{json, Value};
+use std::fmt;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Where undeliverable messages wait for the next run
+pub const QUEUE_PATH: &str = "discord.queue";
+
+// Longest rate limit wait before giving up and queueing the message
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+// Longest the launcher waits for deliveries still running when it exits
+const EXIT_WAIT: Duration = Duration::from_secs(120);
+
+// Requests that failed after every retry, since the launcher started
+static FAILURES: AtomicU64 = AtomicU64::new(0);
+
+// Deliveries run one at a time on a background thread, in the order they were
+// made, so retries and rate limits never hold up the server supervisor
+type Delivery = Box<dyn FnOnce() + Send>;
+static DELIVERIES: OnceLock<Sender<Delivery>> = OnceLock::new();
+static PENDING: AtomicUsize = AtomicUsize::new(0);
+
+// How hard to try delivering a message
+pub struct RetryPolicy {
+    pub attempts: u32,
+    // Delay before the first retry, doubled after each attempt
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            attempts: 5,
+            backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+// Why a message couldn't be delivered
+#[derive(Debug)]
+pub enum DeliveryError {
+    // Retrying won't help, such as a deleted webhook
+    Permanent(String),
+    // Worth trying again later
+    Transient(String),
+}
+
+impl fmt::Display for DeliveryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeliveryError::Permanent(error) | DeliveryError::Transient(error) => {
+                write!(f, "{}", error)
+            }
+        }
+    }
+}
+
+// File uploaded along with a message, embeds can show it as attachment://<filename>
+pub struct Attachment {
+    pub filename: String,
+    pub bytes: Vec<u8>,
+}
+
+// Deliver in the background, after the deliveries made before
+pub fn in_background<F: FnOnce() + Send + 'static>(delivery: F) {
+    let sender = DELIVERIES.get_or_init(|| {
+        let (sender, deliveries) = mpsc::channel::<Delivery>();
+        thread::spawn(move || {
+            for delivery in deliveries {
+                // A delivery going wrong doesn't stop the ones after it
+                if panic::catch_unwind(AssertUnwindSafe(delivery)).is_err() {
+                    println!("[ERROR] Notification delivery panicked");
+                }
+                PENDING.fetch_sub(1, Ordering::SeqCst);
+            }
+        });
+        sender
+    });
+    PENDING.fetch_add(1, Ordering::SeqCst);
+    if sender.send(Box::new(delivery)).is_err() {
+        PENDING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// Wait for background deliveries to finish, so the last notifications aren't lost on exit
+pub fn wait_for_deliveries() {
+    let pending = PENDING.load(Ordering::SeqCst);
+    if pending == 0 {
+        return;
+    }
+    println!(
+        "[INFO] Waiting for {} notifications to be delivered",
+        pending
+    );
+    let started = Instant::now();
+    while PENDING.load(Ordering::SeqCst) > 0 {
+        if started.elapsed() > EXIT_WAIT {
+            println!(
+                "[WARN] Gave up waiting for {} notifications",
+                PENDING.load(Ordering::SeqCst)
+            );
+            return;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+// Post a message, queueing it on disk if it can't be delivered right now
+// Queued messages are sent later without their attachment
+pub fn post(
+    queue: &Path,
+    url: &str,
+    description: &str,
+    content: &Value,
+    attachment: Option<&Attachment>,
+) {
+    post_with(
+        url,
+        description,
+        content,
+        attachment,
+        &RetryPolicy::default(),
+        queue,
+    )
+}
+
+fn post_with(
+    url: &str,
+    description: &str,
+    content: &Value,
+    attachment: Option<&Attachment>,
+    policy: &RetryPolicy,
+    queue: &Path,
+) {
+    let client = reqwest::blocking::Client::new();
+    let result = deliver_request(
+        &client,
+        |client| message_request(client.post(url), content, attachment),
+        policy,
+    );
+    match result.map(|_| ()) {
+        Ok(()) => println!("[INFO] Sent {} message to Discord webhook", description),
+        Err(DeliveryError::Permanent(error)) => println!(
+            "[ERROR] Failed to send {} message to Discord webhook: {}",
+            description, error
+        ),
+        Err(DeliveryError::Transient(error)) => {
+            println!(
+                "[WARN] Failed to send {} message to Discord webhook, queued for next run: {}",
+                description, error
+            );
+            if let Err(error) = enqueue(queue, url, description, content) {
+                println!("[ERROR] Failed to queue {} message: {}", description, error);
+            }
+        }
+    }
+}
+
+// Send messages queued by previous runs to the webhooks they were meant for,
+// keeping the ones that still fail
+// Older launchers only queued for the main webhook and didn't record it, those
+// messages go to `fallback_url`
+pub fn flush_queue(queue: &Path, fallback_url: Option<&str>) {
+    flush_queue_with(fallback_url, &RetryPolicy::default(), queue)
+}
+
+fn flush_queue_with(fallback_url: Option<&str>, policy: &RetryPolicy, queue: &Path) {
+    let contents = match fs::read_to_string(queue) {
+        Ok(contents) => contents,
+        Err(_) => return,
+    };
+    let client = reqwest::blocking::Client::new();
+    let mut remaining = Vec::new();
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+        let entry = match serde_json::from_str::<Value>(line) {
+            Ok(entry) => entry,
+            Err(error) => {
+                println!("[WARN] Dropping unreadable queued message: {}", error);
+                continue;
+            }
+        };
+        let description = entry["description"].as_str().unwrap_or("queued");
+        let url = match entry["url"].as_str().or(fallback_url) {
+            Some(url) => url,
+            None => {
+                println!(
+                    "[WARN] Dropping queued {} message, its webhook is unknown",
+                    description
+                );
+                continue;
+            }
+        };
+        match deliver(&client, url, &entry["content"], policy) {
+            Ok(()) => println!(
+                "[INFO] Sent queued {} message to Discord webhook",
+                description
+            ),
+            Err(DeliveryError::Permanent(error)) => {
+                println!("[ERROR] Dropping queued {} message: {}", description, error)
+            }
+            Err(DeliveryError::Transient(_)) => remaining.push(line),
+        }
+    }
+
+    let result = if remaining.is_empty() {
+        fs::remove_file(queue)
+    } else {
+        println!(
+            "[WARN] {} queued messages still undeliverable",
+            remaining.len()
+        );
+        secrets::write_private(queue, &(remaining.join("\n") + "\n"))
+    };
+    if let Err(error) = result {
+        println!("[ERROR] Failed to update {}: {}", queue.display(), error);
+    }
+}
+
+// Append a message to the on-disk queue, along with the webhook it's for
+// The webhook URL holds its token, so the queue is only readable by its owner
+fn enqueue(queue: &Path, url: &str, description: &str, content: &Value) -> std::io::Result<()> {
+    let mut contents = fs::read_to_string(queue).unwrap_or_default();
+    let entry = json!({"url": url, "description": description, "content": content});
+    contents.push_str(&format!("{}\n", entry));
+    secrets::write_private(queue, &contents)
+}
+
+// Post a message, retrying on rate limits, server errors and network errors
+pub fn deliver(
+    client: &reqwest::blocking::Client,
+    url: &str,
+    content: &Value,
+    policy: &RetryPolicy,
+) -> Result<(), DeliveryError> {
+    deliver_request(client, |client| client.post(url).json(content), policy).map(|_| ())
+}
+
+// Post a message and return its ID, so it can be edited later
+pub fn create(
+    url: &str,
+    content: &Value,
+    attachment: Option<&Attachment>,
+) -> Result<String, DeliveryError> {
+    let client = reqwest::blocking::Client::new();
+    let response = deliver_request(
+        &client,
+        |client| {
+            message_request(
+                client.post(url).query(&[("wait", "true")]),
+                content,
+                attachment,
+            )
+        },
+        &RetryPolicy::default(),
+    )?;
+    match response.json::<Value>() {
+        Ok(message) => match message["id"].as_str() {
+            Some(id) => Ok(id.to_owned()),
+            None => Err(DeliveryError::Permanent(
+                "response has no message ID".to_owned(),
+            )),
+        },
+        Err(error) => Err(DeliveryError::Permanent(without_url(&error))),
+    }
+}
+
+// Replace the contents of a message posted by the webhook
+pub fn edit(url: &str, id: &str, content: &Value) -> Result<(), DeliveryError> {
+    let mut message_url =
+        reqwest::Url::parse(url).map_err(|error| DeliveryError::Permanent(error.to_string()))?;
+    message_url
+        .path_segments_mut()
+        .map_err(|_| DeliveryError::Permanent("invalid webhook URL".to_owned()))?
+        .pop_if_empty()
+        .extend(&["messages", id]);
+    let client = reqwest::blocking::Client::new();
+    deliver_request(
+        &client,
+        |client| client.patch(message_url.clone()).json(content),
+        &RetryPolicy::default(),
+    )
+    .map(|_| ())
+}
+
+// Send the message as JSON, or as multipart form data when uploading a file
+fn message_request(
+    request: reqwest::blocking::RequestBuilder,
+    content: &Value,
+    attachment: Option<&Attachment>,
+) -> reqwest::blocking::RequestBuilder {
+    match attachment {
+        Some(attachment) => {
+            let file = Part::bytes(attachment.bytes.clone()).file_name(attachment.filename.clone());
+            let form = Form::new()
+                .text("payload_json", content.to_string())
+                .part("files[0]", file);
+            request.multipart(form)
+        }
+        None => request.json(content),
+    }
+}
+
+// Send a request built fresh for every attempt, retrying like deliver
+pub fn deliver_request<F>(
+    client: &reqwest::blocking::Client,
+    request: F,
+    policy: &RetryPolicy,
+) -> Result<reqwest::blocking::Response, DeliveryError>
+where
+    F: Fn(&reqwest::blocking::Client) -> reqwest::blocking::RequestBuilder,
+{
+    let result = send_with_retries(client, request, policy);
+    if result.is_err() {
+        FAILURES.fetch_add(1, Ordering::Relaxed);
+    }
+    result
+}
+
+// Number of requests that couldn't be delivered
+pub fn failures() -> u64 {
+    FAILURES.load(Ordering::Relaxed)
+}
+
+fn send_with_retries<F>(
+    client: &reqwest::blocking::Client,
+    request: F,
+    policy: &RetryPolicy,
+) -> Result<reqwest::blocking::Response, DeliveryError>
+where
+    F: Fn(&reqwest::blocking::Client) -> reqwest::blocking::RequestBuilder,
+{
+    let mut backoff = policy.backoff;
+    let mut last_error = String::new();
+    for attempt in 1..=policy.attempts {
+        if attempt > 1 {
+            thread::sleep(backoff);
+            backoff *= 2;
+        }
+
+        let response = match request(client).send() {
+            Ok(response) => response,
+            Err(error) => {
+                last_error = without_url(&error);
+                continue;
+            }
+        };
+        let status = response.status();
+
+        if status.is_success() {
+            // Wait out the bucket if this was the last request allowed
+            if header(&response, "x-ratelimit-remaining").as_deref() == Some("0") {
+                if let Some(reset_after) = seconds_header(&response, "x-ratelimit-reset-after") {
+                    thread::sleep(reset_after.min(MAX_RETRY_AFTER));
+                }
+            }
+            return Ok(response);
+        }
+
+        if status.as_u16() == 429 {
+            // Rate limited, wait as long as the server asks
+            let retry_after = seconds_header(&response, "retry-after").or_else(|| {
+                response
+                    .json::<Value>()
+                    .ok()
+                    .and_then(|body| body["retry_after"].as_f64())
+                    .map(Duration::from_secs_f64)
+            });
+            last_error = format!("HTTP {}", status);
+            match retry_after {
+                Some(retry_after) if retry_after > MAX_RETRY_AFTER => {
+                    return Err(DeliveryError::Transient(format!(
+                        "rate limited for {}s",
+                        retry_after.as_secs()
+                    )))
+                }
+                Some(retry_after) => {
+                    thread::sleep(retry_after);
+                    // Already waited, don't back off on top of it
+                    backoff = policy.backoff;
+                    continue;
+                }
+                None => continue,
+            }
+        }
+
+        if status.is_server_error() {
+            last_error = format!("HTTP {}", status);
+            continue;
+        }
+
+        // Other client errors won't go away by retrying
+        return Err(DeliveryError::Permanent(format!("HTTP {}", status)));
+    }
+    Err(DeliveryError::Transient(last_error))
+}
+
+// Error text without the request URL, which holds the webhook or bot token
+fn without_url(error: &reqwest::Error) -> String {
+    let text = error.to_string();
+    match error.url() {
+        Some(url) => text
+            .replace(&format!(" for url ({})", url.as_str()), "")
+            .replace(url.as_str(), "***"),
+        None => text,
+    }
+}
+
+fn header(response: &reqwest::blocking::Response, name: &str) -> Option<String> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned)
+}
+
+// Read a header holding seconds, possibly fractional
+fn seconds_header(response: &reqwest::blocking::Response, name: &str) -> Option<Duration> {
+    header(response, name)
+        .and_then(|value| value.parse::<f64>().ok())
+        .filter(|seconds| *seconds >= 0.0)
+        .map(Duration::from_secs_f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub::Stub;
+    use std::path::PathBuf;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            attempts: 3,
+            backoff: Duration::from_millis(10),
+        }
+    }
+
+    fn queue_path(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("launcher-{}-{}.queue", std::process::id(), name));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn retries_after_rate_limit() {
+        let stub = Stub::start(vec![
+            (
+                429,
+                vec![("Retry-After", "0.05".to_owned())],
+                "{}".to_owned(),
+            ),
+            (204, Vec::new(), String::new()),
+        ]);
+        let client = reqwest::blocking::Client::new();
+        let result = deliver(&client, &stub.url, &json!({"content": "hi"}), &policy());
+
+        assert!(result.is_ok());
+        assert_eq!(stub.next_request().body, r#"{"content":"hi"}"#);
+        assert_eq!(stub.next_request().body, r#"{"content":"hi"}"#);
+    }
+
+    #[test]
+    fn keeps_tokens_out_of_errors() {
+        // Nothing listens on port 1
+        let url = "http://127.0.0.1:1/bot123:secret-token/sendMessage";
+        let client = reqwest::blocking::Client::new();
+        let policy = RetryPolicy {
+            attempts: 1,
+            backoff: Duration::from_millis(10),
+        };
+        let error = deliver(&client, url, &json!({}), &policy)
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("error sending request"));
+        assert!(!error.contains("secret-token"));
+    }
+
+    #[test]
+    fn does_not_retry_client_errors() {
+        let stub = Stub::start(vec![(404, Vec::new(), "{}".to_owned())]);
+        let queue = queue_path("not-found");
+        post_with(&stub.url, "shutdown", &json!({}), None, &policy(), &queue);
+
+        stub.next_request();
+        stub.assert_no_more_requests();
+        assert!(!queue.exists());
+    }
+
+    #[test]
+    fn queues_and_flushes_undeliverable_messages() {
+        let stub = Stub::start(vec![
+            (500, Vec::new(), String::new()),
+            (502, Vec::new(), String::new()),
+            (503, Vec::new(), String::new()),
+            (204, Vec::new(), String::new()),
+        ]);
+        let queue = queue_path("flush");
+        post_with(
+            &stub.url,
+            "launching",
+            &json!({"content": "up"}),
+            None,
+            &policy(),
+            &queue,
+        );
+
+        for _ in 0..3 {
+            stub.next_request();
+        }
+        let queued = fs::read_to_string(&queue).unwrap();
+        assert!(queued.contains(r#""description":"launching""#));
+        assert!(queued.contains(&format!(r#""url":"{}""#, stub.url)));
+
+        flush_queue_with(None, &policy(), &queue);
+        assert_eq!(stub.next_request().body, r#"{"content":"up"}"#);
+        assert!(!queue.exists());
+    }
+}
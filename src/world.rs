@@ -0,0 +1,252 @@
+// What the world's level.dat says about it
+use crate::context::Context;
+use crate::error::LauncherError;
+use crate::messages;
+use crate::nbt::{self, Tag};
+use crate::properties;
+use chrono::prelude::{DateTime, Local, TimeZone};
+use std::fs::{self, File};
+use std::io::{self, ErrorKind, Read};
+use std::path::Path;
+
+pub struct WorldInfo {
+    // Version of the world format, raised by every Minecraft release
+    pub data_version: Option<i64>,
+    // Minecraft version that last saved the world, such as 1.16.5
+    pub version_name: Option<String>,
+    pub seed: Option<i64>,
+    pub spawn: Option<(i64, i64, i64)>,
+    pub game_rules: Vec<(String, String)>,
+    pub last_played: Option<DateTime<Local>>,
+    // 0 peaceful to 3 hard
+    pub difficulty: Option<i64>,
+    pub hardcore: bool,
+}
+
+impl WorldInfo {
+    // Read level.dat in the world directory
+    pub fn load(world_path: &Path) -> io::Result<WorldInfo> {
+        WorldInfo::from_tag(&nbt::read_gzip(&world_path.join("level.dat"))?)
+    }
+
+    fn from_tag(root: &Tag) -> io::Result<WorldInfo> {
+        let data = match root.get("Data") {
+            Some(data) => data,
+            None => {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    "level.dat has no Data compound",
+                ))
+            }
+        };
+        let number = |name: &str| data.get(name).and_then(Tag::as_i64);
+        // 1.16 moved the seed into the world generation settings
+        let seed = data
+            .get("WorldGenSettings")
+            .and_then(|settings| settings.get("seed"))
+            .and_then(Tag::as_i64)
+            .or_else(|| number("RandomSeed"));
+        let spawn = match (number("SpawnX"), number("SpawnY"), number("SpawnZ")) {
+            (Some(x), Some(y), Some(z)) => Some((x, y, z)),
+            _ => None,
+        };
+        let game_rules = match data.get("GameRules") {
+            Some(Tag::Compound(rules)) => rules
+                .iter()
+                .filter_map(|(rule, value)| Some((rule.clone(), value.as_str()?.to_owned())))
+                .collect(),
+            _ => Vec::new(),
+        };
+        Ok(WorldInfo {
+            data_version: number("DataVersion"),
+            version_name: data
+                .get("Version")
+                .and_then(|version| version.get("Name"))
+                .and_then(Tag::as_str)
+                .map(str::to_owned),
+            seed,
+            spawn,
+            game_rules,
+            last_played: number("LastPlayed")
+                .and_then(|millis| Local.timestamp_millis_opt(millis).single()),
+            difficulty: number("Difficulty"),
+            hardcore: number("hardcore").unwrap_or(0) != 0,
+        })
+    }
+
+    // Difficulty name, with hardcore worlds marked
+    pub fn difficulty_label(&self) -> Option<String> {
+        let difficulty = match self.difficulty? {
+            0 => messages::get("difficulty.peaceful"),
+            1 => messages::get("difficulty.easy"),
+            2 => messages::get("difficulty.normal"),
+            3 => messages::get("difficulty.hard"),
+            _ => return None,
+        };
+        if self.hardcore {
+            Some(format!(
+                "{} ({})",
+                difficulty,
+                messages::get("difficulty.hardcore")
+            ))
+        } else {
+            Some(difficulty)
+        }
+    }
+
+    // Labelled details for notifications, the seed is left out on purpose
+    pub fn fields(&self) -> Vec<(String, String)> {
+        let mut fields = Vec::new();
+        if let Some(version_name) = &self.version_name {
+            fields.push((messages::get("field.world-version"), version_name.clone()));
+        }
+        if let Some(difficulty) = self.difficulty_label() {
+            fields.push((messages::get("field.difficulty"), difficulty));
+        }
+        if let Some(last_played) = self.last_played {
+            fields.push((
+                messages::get("field.last-played"),
+                last_played.format("%Y-%m-%d %H:%M").to_string(),
+            ));
+        }
+        fields
+    }
+}
+
+// DataVersion of worlds saved by a server jar, from its version.json
+pub fn jar_data_version(path: &Path) -> Option<i64> {
+    let file = File::open(path).ok()?;
+    let mut archive = zip::ZipArchive::new(file).ok()?;
+    let mut contents = String::new();
+    archive
+        .by_name("version.json")
+        .ok()?
+        .read_to_string(&mut contents)
+        .ok()?;
+    serde_json::from_str::<serde_json::Value>(&contents).ok()?["world_version"].as_i64()
+}
+
+// Worlds can't be opened safely by a version older than the one that last saved them
+pub fn check_compatibility(world: &WorldInfo, jar_path: &Path) -> Result<(), String> {
+    match (world.data_version, jar_data_version(jar_path)) {
+        (Some(world_version), Some(jar_version)) if jar_version < world_version => Err(format!(
+            "World was saved by {} (data version {}), {} only knows data version {}",
+            world.version_name.as_deref().unwrap_or("a newer version"),
+            world_version,
+            jar_path.display(),
+            jar_version
+        )),
+        _ => Ok(()),
+    }
+}
+
+// `world info [<level name>]`, print what level.dat says about the world
+pub fn run(context: &Context, args: &[String]) -> Result<(), LauncherError> {
+    let contents = fs::read_to_string(context.path("server.properties")).unwrap_or_default();
+    let level_name = match args {
+        [command] if command == "info" => match properties::get(&contents, "level-name") {
+            Some(level_name) => level_name,
+            None => {
+                return Err(LauncherError::Config(
+                    "level-name missing from server.properties!".to_owned(),
+                ))
+            }
+        },
+        [command, level_name] if command == "info" => level_name.as_str(),
+        _ => return Err(LauncherError::Usage("world info [<level name>]".to_owned())),
+    };
+    let world = WorldInfo::load(&context.path(level_name)).map_err(|error| {
+        LauncherError::Data(format!(
+            "Failed to read {}/level.dat: {}",
+            level_name, error
+        ))
+    })?;
+
+    let unknown = || "?".to_owned();
+    println!("Level:        {}", level_name);
+    println!(
+        "Version:      {} (data version {})",
+        world.version_name.clone().unwrap_or_else(unknown),
+        world
+            .data_version
+            .map(|version| version.to_string())
+            .unwrap_or_else(unknown)
+    );
+    println!(
+        "Seed:         {}",
+        world
+            .seed
+            .map(|seed| seed.to_string())
+            .unwrap_or_else(unknown)
+    );
+    println!(
+        "Spawn:        {}",
+        world
+            .spawn
+            .map(|(x, y, z)| format!("{}, {}, {}", x, y, z))
+            .unwrap_or_else(unknown)
+    );
+    println!(
+        "Difficulty:   {}",
+        world.difficulty_label().unwrap_or_else(unknown)
+    );
+    println!(
+        "Last played:  {}",
+        world
+            .last_played
+            .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(unknown)
+    );
+    if world.game_rules.is_empty() {
+        println!("Game rules:   none set");
+    } else {
+        println!("Game rules:");
+        for (rule, value) in &world.game_rules {
+            println!("  {} = {}", rule, value);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> WorldInfo {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name);
+        WorldInfo::load(&path).unwrap()
+    }
+
+    #[test]
+    fn reads_level_dat() {
+        let world = fixture("world-1.16.5");
+
+        assert_eq!(world.data_version, Some(2586));
+        assert_eq!(world.version_name.as_deref(), Some("1.16.5"));
+        assert_eq!(world.seed, Some(-4172144997902289642));
+        assert_eq!(world.spawn, Some((-128, 64, 256)));
+        assert_eq!(world.game_rules.len(), 3);
+        assert_eq!(
+            world.game_rules[0],
+            ("doDaylightCycle".to_owned(), "true".to_owned())
+        );
+        assert_eq!(
+            world.last_played.map(|time| time.timestamp()),
+            Some(1620000000)
+        );
+        assert_eq!(world.difficulty, Some(2));
+        assert!(!world.hardcore);
+    }
+
+    #[test]
+    fn reads_seed_of_worlds_before_1_16() {
+        let world = fixture("world-1.12.2");
+
+        assert_eq!(world.data_version, Some(1343));
+        assert_eq!(world.seed, Some(42));
+        assert_eq!(world.difficulty, Some(3));
+        assert!(world.hardcore);
+    }
+}
@@ -0,0 +1,6 @@
+// Helpers shared by the integration tests
+#![allow(dead_code)]
+
+// The same HTTP stub the unit tests use
+#[path = "../../src/stub.rs"]
+pub mod stub;
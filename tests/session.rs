@@ -0,0 +1,215 @@
+// Whole sessions run against a fake server and a local webhook, in simulated time
+use chrono::prelude::{DateTime, Local};
+use chrono::Duration;
+use minecraft_server_launcher::clock::Clock;
+use minecraft_server_launcher::context::Context;
+use minecraft_server_launcher::notify::Notifiers;
+use minecraft_server_launcher::run_session;
+use minecraft_server_launcher::status::SharedStatus;
+use serde_json::Value;
+use std::env;
+use std::fs;
+use std::net::TcpListener;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::mpsc;
+use std::time::Instant;
+
+mod common;
+use common::stub::Stub;
+
+// Stands in for java, answers -version and acts like a server on the console
+const FAKE_SERVER: &str = r#"#!/bin/sh
+if [ "$1" = "-version" ]; then
+    echo 'openjdk version "17.0.2" 2022-01-18' >&2
+    exit 0
+fi
+echo '[12:00:00] [Server thread/INFO]: Starting minecraft server version 1.16.5'
+echo '[12:00:01] [Server thread/INFO]: Done (1.000s)! For help, type "help"'
+# Whatever the test wants players to do
+if [ -f players.log ]; then
+    cat players.log
+fi
+while read -r command; do
+    echo "$command" >> commands.log
+    if [ "$command" = "stop" ]; then
+        echo '[12:00:02] [Server thread/INFO]: Stopping the server'
+        exit 0
+    fi
+done
+"#;
+
+// Server directory with everything needed to launch Minecraft 1.16.5
+fn server_root(name: &str, webhook_url: &str) -> PathBuf {
+    let root = env::temp_dir().join(format!("launcher-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("jars")).unwrap();
+
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let properties = format!(
+        "level-name=world\nserver-version=1.16.5\nmotd=A Minecraft Server\nserver-ip=127.0.0.1\nserver-port={}\npreflight-min-free-mb=0\n",
+        port
+    );
+    fs::write(root.join("server.properties"), properties).unwrap();
+    fs::write(root.join("eula.txt"), "eula=true\n").unwrap();
+    fs::write(root.join("jars/1.16.5.jar"), "not really a jar").unwrap();
+    fs::write(
+        root.join("jars/checksums.json"),
+        r#"{"1.16.5": "d2c6cf77ae5f94f752b1bb51027081935c2487ad68f670e6d230f41c93d5c547"}"#,
+    )
+    .unwrap();
+    fs::write(
+        root.join("notifiers.json"),
+        format!(r#"[{{"type": "webhook", "url": "{}"}}]"#, webhook_url),
+    )
+    .unwrap();
+    fs::set_permissions(
+        root.join("notifiers.json"),
+        fs::Permissions::from_mode(0o600),
+    )
+    .unwrap();
+
+    let fake_server = root.join("fake-server.sh");
+    fs::write(&fake_server, FAKE_SERVER).unwrap();
+    fs::set_permissions(&fake_server, fs::Permissions::from_mode(0o755)).unwrap();
+    root
+}
+
+// Run the server in the directory for ten minutes, where five minutes pass every real second
+// Returns whether the session went fine and the events posted to the webhook
+fn run_ten_minutes(root: PathBuf, webhook: &Stub) -> (bool, Vec<String>) {
+    let context = Context {
+        java: root.join("fake-server.sh"),
+        clock: Clock::simulated(Local::now(), 300),
+        root,
+        ..Context::default()
+    };
+    let notifiers = Notifiers::load(&context, "Test Launcher", None);
+    let (_control_sender, controls) = mpsc::channel();
+    let started = Instant::now();
+    let result = run_session(
+        &context,
+        &notifiers,
+        Some(context.clock.now() + Duration::minutes(10)),
+        &controls,
+        &SharedStatus::default(),
+    );
+    // Notifications are delivered in the background, wait for them
+    drop(notifiers);
+    assert!(started.elapsed() < std::time::Duration::from_secs(30));
+
+    let events = webhook
+        .requests()
+        .iter()
+        .map(|request| {
+            let content = serde_json::from_str::<Value>(&request.body).unwrap();
+            content["event"].as_str().unwrap_or_default().to_owned()
+        })
+        .collect();
+    (result.is_ok(), events)
+}
+
+fn add_properties(root: &Path, properties: &str) {
+    let contents = fs::read_to_string(root.join("server.properties")).unwrap();
+    fs::write(root.join("server.properties"), contents + properties).unwrap();
+}
+
+// Commands the launcher sent to the fake server
+fn commands(root: &Path) -> Vec<String> {
+    fs::read_to_string(root.join("commands.log"))
+        .unwrap()
+        .lines()
+        .map(str::to_owned)
+        .collect()
+}
+
+#[test]
+fn runs_a_session_until_the_scheduled_shutdown() {
+    let webhook = Stub::start(Vec::new());
+    let root = server_root("session", &format!("{}/hook", webhook.url));
+    let (ok, events) = run_ten_minutes(root.clone(), &webhook);
+
+    assert!(ok);
+    assert_eq!(
+        events,
+        ["launching", "reminder", "reminder", "reminder", "shutdown"]
+    );
+    let root = &root;
+
+    // Reminders went to the players before the server was saved and stopped
+    let commands = commands(root);
+    assert_eq!(
+        commands
+            .iter()
+            .filter(|command| command.starts_with("tellraw @a"))
+            .count(),
+        4
+    );
+    assert_eq!(commands[commands.len() - 2..], ["save-all", "stop"]);
+
+    // The session cleaned up after itself
+    let properties = fs::read_to_string(root.join("server.properties")).unwrap();
+    assert!(properties.contains("motd=A Minecraft Server"));
+    assert!(!root.join("server.lock").exists());
+    let _ = fs::remove_dir_all(root);
+}
+
+#[test]
+fn players_vote_to_extend_the_session() {
+    let webhook = Stub::start(Vec::new());
+    let root = server_root("vote", &format!("{}/hook", webhook.url));
+    add_properties(&root, "extend-amount=5m\n");
+    fs::write(
+        root.join("players.log"),
+        "[12:00:02] [Server thread/INFO]: Steve joined the game\n\
+         [12:00:03] [Server thread/INFO]: <Steve> !extend\n",
+    )
+    .unwrap();
+    let (ok, events) = run_ten_minutes(root.clone(), &webhook);
+
+    assert!(ok);
+    assert_eq!(events.first().map(String::as_str), Some("launching"));
+    assert_eq!(events.last().map(String::as_str), Some("shutdown"));
+
+    // The only player online carried the vote, the shutdown moved five minutes
+    // Messages are in the default pt-PT
+    assert!(commands(&root)
+        .iter()
+        .any(|command| command.contains("Encerramento do servidor adiado")));
+    let history = fs::read_to_string(root.join("history.jsonl")).unwrap();
+    let session = serde_json::from_str::<Value>(&history).unwrap();
+    let time = |key: &str| DateTime::parse_from_rfc3339(session[key].as_str().unwrap()).unwrap();
+    let length = time("scheduled_time") - time("start_time");
+    assert!(length > Duration::minutes(14) && length <= Duration::minutes(15));
+    assert_eq!(session["players"], serde_json::json!(["Steve"]));
+    let _ = fs::remove_dir_all(&root);
+}
+
+#[test]
+fn restarts_the_server_once_it_is_empty() {
+    let webhook = Stub::start(Vec::new());
+    let root = server_root("restart", &format!("{}/hook", webhook.url));
+    add_properties(&root, "restart-when-empty=6m\n");
+    let (ok, events) = run_ten_minutes(root.clone(), &webhook);
+
+    assert!(ok);
+    assert_eq!(events.first().map(String::as_str), Some("launching"));
+    assert_eq!(events.last().map(String::as_str), Some("shutdown"));
+    // Up six minutes with nobody online, relaunched too late for a second restart
+    assert_eq!(
+        events.iter().filter(|event| *event == "restarting").count(),
+        1
+    );
+    let commands = commands(&root);
+    assert_eq!(
+        commands.iter().filter(|command| *command == "stop").count(),
+        2
+    );
+    assert!(!root.join("server.lock").exists());
+    let _ = fs::remove_dir_all(&root);
+}